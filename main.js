@@ -1,6 +1,30 @@
 import init, { run_app } from './pkg/avisha.js';
+
+// ACTOR is the local storage key for the name changes are recorded under.
+const ACTOR = 'yew.avisha.actor';
+
+// actor asks who is using the app the first time it is opened, and remembers
+// the answer.
+function actor() {
+   let name = localStorage.getItem(ACTOR);
+   if (!name) {
+      name = (prompt('Who is using Avisha? Changes are recorded under this name.') || '').trim();
+      if (!name) {
+         return 'Property Manager';
+      }
+      localStorage.setItem(ACTOR, name);
+   }
+   return name;
+}
+
+// store is where data is saved, named by the `store` query parameter, as in
+// `?store=indexeddb`. Local storage is used if none is named.
+function store() {
+   return new URLSearchParams(window.location.search).get('store');
+}
+
 async function main() {
    await init('/pkg/avisha_bg.wasm');
-   run_app();
+   await run_app(store(), actor());
 }
-main()
\ No newline at end of file
+main()
@@ -1,101 +1,149 @@
+use crate::bill_form::{Form as BillForm, Model as BillFormModel};
+use crate::business_form::{Form as BusinessForm, Model as BusinessFormModel};
+use crate::domain::{
+    Audited, Contact, Event, Invoice, Lease, LeaseId, Money, Site, SiteId, State, Status, Subject,
+    Tenant, TenantId, Towards,
+};
+use crate::history::History;
 use crate::lease_form::{self, Form as LeaseForm, Model as LeaseFormModel};
+use crate::meter_form::{Form as MeterForm, Model as MeterFormModel};
+use crate::payment_form::{Form as PaymentForm, Model as PaymentFormModel};
+use crate::reading_form::{Form as ReadingForm, Model as ReadingFormModel};
+use crate::rename_form::{Form as RenameForm, Model as RenameFormModel};
+use crate::renew_form::{Form as RenewForm, Model as RenewFormModel};
 use crate::site_form::{self, Form as SiteForm, Model as SiteFormModel};
+use crate::tariff_form::{Form as TariffForm, Model as TariffFormModel};
 use crate::tenant_form::{self, Form as TenantForm, Model as TenantFormModel};
-use crate::validate::{SiteValidator, TenantValidator, Validate};
+use crate::terminate_form::{Form as TerminateForm, Model as TerminateFormModel};
+use crate::validate::{
+    LeaseValidator, MeterValidator, PaymentValidator, ReadingValidator, SiteValidator,
+    TariffValidator, TenantValidator, Validate,
+};
 
+use crate::pdf;
+use crate::store::{self, Memory, Store};
 use chrono::{Local, NaiveDate as Date};
-use serde_derive::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fmt;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
 use std::rc::Rc;
-use strum_macros::{Display, EnumIter};
-use yew::format::Json;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, Element, File, HtmlAnchorElement, KeyboardEvent, Url};
 use yew::prelude::*;
-use yew::services::storage::{Area, StorageService};
-use yew::services::ConsoleService;
-
-const KEY: &str = "yew.avisha.self";
-
-// Days is a duration in days.
-type Days = u32;
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
-pub struct Tenant {
-    pub name: String, // primary key
-    pub contact: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
-pub struct Site {
-    pub number: String, // primary key
-    pub kind: SiteKind,
-    pub lease: Option<Lease>,
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
-pub enum SiteKind {
-    Cabin,
-    Flat,
-    House,
-    Other(String),
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
-pub struct Lease {
-    pub tenant_name: String,
-    pub site_number: String,
-    pub term: Term,
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
-pub struct Term {
-    pub start: Date,
-    pub duration: Days,
-    pub rent: u32,
-}
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
 
 pub struct App {
     state: State,
-    storage: StorageService,
+    errors: Vec<String>,
+    selected_lease: Option<LeaseId>,
+    store: Rc<RefCell<Box<dyn Store>>>,
+    history: History,
+    actor: String,
+    // The audit trail being viewed, and what it is viewed for.
+    audit: Option<(Subject, Vec<Audited>)>,
+    // Listens for undo and redo shortcuts for as long as the app runs.
+    keys: Option<KeyListenerHandle>,
+    reader: ReaderService,
+    // Keeps a file being imported alive until it has been read.
+    import: Option<ReaderTask>,
     link: ComponentLink<Self>,
 }
 
-#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
-pub struct State {
-    pub tenants: HashMap<String, Tenant>,
-    pub sites: HashMap<String, Site>,
-    pub leases: HashSet<Lease>,
-
-    pub errors: Vec<String>,
-}
-
 pub enum Msg {
     RegisterTenant(TenantFormModel),
+    RenameTenant(TenantId, RenameFormModel),
     ListSite(SiteFormModel),
+    RenumberSite(SiteId, RenameFormModel),
     LeaseSite(LeaseFormModel),
+    ActivateLease(LeaseId),
+    ExpireLease(LeaseId),
+    TerminateLease(LeaseId, TerminateFormModel),
+    RenewLease(LeaseId, RenewFormModel),
+    SelectLease(Option<LeaseId>),
+    InstallMeter(MeterFormModel),
+    RecordReading(ReadingFormModel),
+    SetTariff(TariffFormModel),
+    IssueUtilityBill(LeaseId, BillFormModel),
+    RecordPayment(PaymentFormModel),
+    IssueInvoice(LeaseId),
+    IssueInvoices,
+    Undo,
+    Redo,
+    ShowAudit(Subject),
+    HideAudit,
+    ExportAudit,
+    SetBusiness(BusinessFormModel),
+    DownloadInvoice(u32),
+    DownloadStatement(LeaseId),
+    ExportData,
+    ImportData(Vec<File>),
+    Imported(FileData),
     DismissErr(usize),
     Nope,
 }
 
+#[derive(Properties, Clone)]
+pub struct Props {
+    /// Where state is saved, chosen when the app starts.
+    pub store: Rc<RefCell<Box<dyn Store>>>,
+    /// Problems opening the store, shown once the app has started.
+    pub errors: Vec<String>,
+    /// Who changes are recorded as made by in the audit trail.
+    pub actor: String,
+}
+
 impl Component for App {
     type Message = Msg;
-    type Properties = ();
-
-    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let storage = StorageService::new(Area::Local).expect("no local storage");
+    type Properties = Props;
 
-        let state = {
-            if let Json(Ok(restored_model)) = storage.restore(KEY) {
-                restored_model
-            } else {
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let Props {
+            store,
+            mut errors,
+            actor,
+        } = props;
+        let loaded = store.borrow_mut().load();
+        let mut state = match loaded {
+            Ok(state) => state.unwrap_or_default(),
+            // The store has set aside what it could not read and saved what
+            // it could in its place, so saving over it loses nothing.
+            Err(err @ store::Error::Quarantined { .. }) => {
+                errors.push(format!(
+                    "Could not load saved data: {}. Avisha has started without it.",
+                    err
+                ));
+                store.borrow_mut().load().ok().flatten().unwrap_or_default()
+            }
+            // Saved data may still be there, so nothing is saved over it.
+            Err(err) => {
+                errors.push(format!(
+                    "Could not load saved data: {}. Changes will not be saved.",
+                    err
+                ));
+                *store.borrow_mut() = Box::new(Memory::default());
                 State::default()
             }
         };
+        let due = Event::RentFellDue { on: today() };
+        if let Err(err) = store.borrow_mut().record(&mut state, due, &actor) {
+            errors.push(format!("Could not save rent due: {}", err));
+        }
+        let keys = web_sys::window()
+            .map(|window| KeyboardService::register_key_down(&window, link.callback(shortcut)));
 
         App {
             link,
             state,
-            storage,
+            errors,
+            selected_lease: None,
+            store,
+            history: History::default(),
+            actor,
+            audit: None,
+            keys,
+            reader: ReaderService::new(),
+            import: None,
         }
     }
 
@@ -104,49 +152,207 @@ impl Component for App {
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
-        match msg {
-            Msg::RegisterTenant(TenantFormModel { name, contact }) => {
-                self.state
-                    .tenants
-                    .insert(name.clone(), Tenant { name, contact });
+        let result = match msg {
+            Msg::RegisterTenant(model) => match model.contact() {
+                Ok(contact) => self.record(Event::TenantRegistered(Tenant {
+                    id: Default::default(),
+                    name: model.name,
+                    contact,
+                })),
+                Err(errors) => return self.field_errors(errors),
+            },
+            Msg::RenameTenant(tenant, RenameFormModel { name }) => {
+                self.record(Event::TenantRenamed { tenant, name })
             }
-            Msg::ListSite(SiteFormModel { number, kind }) => {
-                self.state.sites.insert(
-                    number.clone(),
-                    Site {
-                        number,
-                        kind: kind.into(),
-                        lease: None,
-                    },
+            Msg::ListSite(SiteFormModel { number, kind }) => self.record(Event::SiteListed(Site {
+                id: Default::default(),
+                number,
+                kind: kind.into(),
+                leases: vec![],
+                meters: vec![],
+            })),
+            Msg::RenumberSite(site, RenameFormModel { name }) => {
+                self.record(Event::SiteRenumbered { site, number: name })
+            }
+            Msg::LeaseSite(model) => match model.lease() {
+                Ok(lease) => self.record(Event::LeaseCreated(lease)),
+                Err(errors) => return self.field_errors(errors),
+            },
+            Msg::ActivateLease(lease) => self.record(Event::LeaseActivated { lease, on: today() }),
+            Msg::ExpireLease(lease) => self.record(Event::LeaseExpired { lease, on: today() }),
+            Msg::TerminateLease(lease, TerminateFormModel { end, reason }) => match end.parse() {
+                Ok(end) => self.record(Event::LeaseTerminated {
+                    lease,
+                    end,
+                    reason,
+                    on: today(),
+                }),
+                Err(_) => return self.error(format!("invalid date: {}", end)),
+            },
+            Msg::RenewLease(lease, model) => match model.parse() {
+                Ok((duration, rent)) => self.record(Event::LeaseRenewed {
+                    lease,
+                    duration,
+                    rent,
+                    on: today(),
+                }),
+                Err(errors) => return self.field_errors(errors),
+            },
+            Msg::InstallMeter(model) => self.record(Event::MeterInstalled {
+                site: model.site.id,
+                meter: model.meter(),
+            }),
+            Msg::RecordReading(model) => match model.reading() {
+                Ok(reading) => self.record(Event::ReadingRecorded {
+                    site: model.site.id,
+                    meter: model.meter.name,
+                    reading,
+                }),
+                Err(errors) => return self.field_errors(errors),
+            },
+            Msg::SetTariff(model) => match model.tariff() {
+                Ok(tariff) => self.record(Event::TariffSet(tariff)),
+                Err(errors) => return self.field_errors(errors),
+            },
+            Msg::IssueUtilityBill(lease, BillFormModel { to }) => match to.parse() {
+                Ok(to) => self.record(Event::UtilityBillIssued {
+                    lease,
+                    to,
+                    on: today(),
+                }),
+                Err(_) => return self.error(format!("invalid date: {}", to)),
+            },
+            Msg::RecordPayment(model) => match model.payment() {
+                Ok(payment) => self.record(Event::PaymentRecorded(payment)),
+                Err(errors) => return self.field_errors(errors),
+            },
+            Msg::IssueInvoice(lease) => self.record(Event::InvoiceIssued { lease, on: today() }),
+            Msg::IssueInvoices => self.record(Event::InvoicesIssued { on: today() }),
+            Msg::SetBusiness(model) => self.record(Event::BusinessSet(model.into())),
+            Msg::Undo => {
+                let mut store = self.store.borrow_mut();
+                self.history.undo(&mut *store, &mut self.state, &self.actor)
+            }
+            Msg::Redo => {
+                let mut store = self.store.borrow_mut();
+                self.history.redo(&mut *store, &mut self.state, &self.actor)
+            }
+            Msg::ShowAudit(subject) => {
+                let audit = self.store.borrow_mut().audit();
+                match audit {
+                    Ok(audit) => {
+                        self.audit = Some((subject, audit));
+                        return true;
+                    }
+                    Err(err) => return self.error(format!("could not read audit trail: {}", err)),
+                }
+            }
+            Msg::HideAudit => {
+                self.audit = None;
+                return true;
+            }
+            Msg::ExportAudit => {
+                let name = format!("avisha-audit-{}.json", today());
+                let exported = self
+                    .store
+                    .borrow_mut()
+                    .audit()
+                    .map_err(|err| err.to_string())
+                    .and_then(|audit| {
+                        serde_json::to_string_pretty(&audit).map_err(|err| err.to_string())
+                    })
+                    .and_then(|data| {
+                        download(&name, JSON, data.as_bytes())
+                            .map_err(|_| "download failed".to_string())
+                    });
+                if let Err(err) = exported {
+                    return self.error(format!("could not export audit trail: {}", err));
+                }
+                return false;
+            }
+            Msg::DownloadInvoice(number) => {
+                if let Some(invoice) = self.state.invoices.iter().find(|i| i.number == number) {
+                    let document = pdf::invoice::invoice(&self.state, invoice);
+                    let name = format!("invoice-{}.pdf", number);
+                    if download(&name, PDF, &document.to_bytes()).is_err() {
+                        return self.error(format!("could not download invoice #{}", number));
+                    }
+                }
+                return false;
+            }
+            Msg::DownloadStatement(at) => {
+                let lease = match self.state.lease(at) {
+                    Some(lease) => lease,
+                    None => return false,
+                };
+                let document = pdf::invoice::statement(&self.state, at, today());
+                let name = format!(
+                    "statement-{}-{}.pdf",
+                    self.state.site_number(lease.site),
+                    lease.term.start
                 );
+                if download(&name, PDF, &document.to_bytes()).is_err() {
+                    return self.error("could not download statement".into());
+                }
+                return false;
+            }
+            Msg::ExportData => {
+                let name = format!("avisha-{}.json", today());
+                let exported = store::to_string(&self.state)
+                    .map_err(|err| err.to_string())
+                    .and_then(|data| {
+                        download(&name, JSON, data.as_bytes())
+                            .map_err(|_| "download failed".to_string())
+                    });
+                if let Err(err) = exported {
+                    return self.error(format!("could not export data: {}", err));
+                }
+                return false;
             }
-            Msg::LeaseSite(LeaseFormModel {
-                site,
-                tenant,
-                start,
-                duration,
-                rent,
-            }) => {
-                ConsoleService::log(&format!("attempting to create lease"));
-                // TOOD: Handle data parsing.
-                // Should this happen at the form level? (I think so).
-                self.state.leases.insert(Lease {
-                    tenant_name: tenant.name,
-                    site_number: site.number,
-                    term: Term {
-                        start: start.parse().expect("parsing date"),
-                        duration: duration.parse().expect("parsing duration"),
-                        rent: rent.parse().expect("parsing rent"),
+            Msg::ImportData(files) => {
+                if let Some(file) = files.into_iter().next() {
+                    let callback = self.link.callback(Msg::Imported);
+                    match self.reader.read_file(file, callback) {
+                        Ok(task) => self.import = Some(task),
+                        Err(_) => return self.error("could not read file".into()),
+                    }
+                }
+                return false;
+            }
+            Msg::Imported(data) => {
+                self.import = None;
+                match store::from_str(&String::from_utf8_lossy(&data.content)) {
+                    Ok(file) => match Event::imported(&self.state, &file) {
+                        Event::Imported {
+                            deliveries,
+                            notifications,
+                        } if deliveries.is_empty() && notifications.is_empty() => {
+                            return self.error(format!("nothing new to import from {}", data.name))
+                        }
+                        imported => self.record(imported),
                     },
-                });
+                    Err(err) => {
+                        return self.error(format!("could not import {}: {}", data.name, err))
+                    }
+                }
+            }
+            Msg::SelectLease(at) => {
+                self.selected_lease = at;
+                return true;
             }
             Msg::DismissErr(ii) => {
-                self.state.errors.remove(ii);
+                self.errors.remove(ii);
+                Ok(())
             }
-            Msg::Nope => {}
+            Msg::Nope => return false,
         };
 
-        self.storage.store(KEY, Json(&self.state));
+        if let Err(err) = result {
+            if let Some(store::Error::Storage(_)) = err.downcast_ref::<store::Error>() {
+                return self.reload(err.to_string());
+            }
+            return self.error(err.to_string());
+        }
         true
     }
 
@@ -154,7 +360,7 @@ impl Component for App {
         let dismiss_err =
             |ii: usize| -> Callback<_> { self.link.callback(move |_| Msg::DismissErr(ii)) };
 
-        let errors = self.state.errors.iter().enumerate();
+        let errors = self.errors.iter().enumerate();
 
         // Fixme: How to avoid cloning the data just to pass it in?
         // - SiteValidator doesn't need access to the data,
@@ -165,6 +371,18 @@ impl Component for App {
         let tenant_validator = TenantValidator {
             tenants: self.state.tenants.clone(),
         };
+        let lease_validator = LeaseValidator {
+            state: self.state.clone(),
+        };
+        let meter_validator = MeterValidator {
+            sites: self.state.sites.clone(),
+        };
+        let reading_validator = ReadingValidator {
+            sites: self.state.sites.clone(),
+        };
+        let payment_validator = PaymentValidator {
+            state: self.state.clone(),
+        };
 
         html! {
             <div>
@@ -222,12 +440,76 @@ impl Component for App {
                                         {"Enter Lease"}
                                     </h5>
                                     <div class="card-body padded">
-                                        <LeaseForm
+                                        <LeaseForm::<LeaseValidator>
                                             submit=self.link.callback(|v| Msg::LeaseSite(v))
                                             state=self.state.clone()
+                                            validator=lease_validator
                                         />
                                     </div>
                                 </div>
+                                <div class="card">
+                                    <h5 class="card-header">
+                                        {"Record Payment"}
+                                    </h5>
+                                    <div class="card-body padded">
+                                        <PaymentForm::<PaymentValidator>
+                                            submit=self.link.callback(|v| Msg::RecordPayment(v))
+                                            state=self.state.clone()
+                                            validator=payment_validator
+                                        />
+                                    </div>
+                                </div>
+                                <div class="card">
+                                    <h5 class="card-header">
+                                        {"Install Meter"}
+                                    </h5>
+                                    <div class="card-body padded">
+                                        <MeterForm::<MeterValidator>
+                                            submit=self.link.callback(|v| Msg::InstallMeter(v))
+                                            state=self.state.clone()
+                                            validator=meter_validator
+                                        />
+                                    </div>
+                                </div>
+                                <div class="card">
+                                    <h5 class="card-header">
+                                        {"Record Meter Reading"}
+                                    </h5>
+                                    <div class="card-body padded">
+                                        <ReadingForm::<ReadingValidator>
+                                            submit=self.link.callback(|v| Msg::RecordReading(v))
+                                            state=self.state.clone()
+                                            validator=reading_validator
+                                        />
+                                    </div>
+                                </div>
+                                <div class="card">
+                                    <h5 class="card-header">
+                                        {"Business Details"}
+                                    </h5>
+                                    <div class="card-body padded">
+                                        <BusinessForm
+                                            submit=self.link.callback(|v| Msg::SetBusiness(v))
+                                            business=self.state.business.clone()
+                                        />
+                                    </div>
+                                </div>
+                                {self.data_card()}
+                                <div class="card">
+                                    <h5 class="card-header">
+                                        {"Utility Tariffs"}
+                                    </h5>
+                                    <div class="card-body padded">
+                                        <TariffForm::<TariffValidator>
+                                            submit=self.link.callback(|v| Msg::SetTariff(v))
+                                            state=self.state.clone()
+                                            validator=TariffValidator
+                                        />
+                                        {for self.state.tariffs.iter().map(|t| html! {
+                                            <p>{t}</p>
+                                        })}
+                                    </div>
+                                </div>
                             </div>
                         </div>
                     </div>
@@ -242,6 +524,21 @@ impl Component for App {
                             {self.lease_list()}
                         </div>
                     </div>
+                    <div class="row">
+                        <div class="col">
+                            {self.arrears_list()}
+                        </div>
+                        <div class="col">
+                            {self.invoice_list()}
+                        </div>
+                    </div>
+                    {self.lease_detail()}
+                    <div class="row">
+                        <div class="col">
+                            {self.history_list()}
+                        </div>
+                    </div>
+                    {self.audit_trail()}
                 </div>
 
             </div>
@@ -250,7 +547,139 @@ impl Component for App {
 }
 
 impl App {
+    fn data_card(&self) -> Html {
+        html! {
+            <div class="card">
+                <h5 class="card-header">
+                    {"Data"}
+                </h5>
+                <div class="card-body padded">
+                    <p>{"Export data to send invoices by email with avisha-mail, then import it again to see what was sent."}</p>
+                    <button onclick=self.link.callback(|_| Msg::ExportData)>
+                        {"Export Data"}
+                    </button>
+                    <button onclick=self.link.callback(|_| Msg::ExportAudit)>
+                        {"Export Audit Trail"}
+                    </button>
+                    {self.audit_button(Subject::Business)}
+                    <input
+                        type="file"
+                        accept="application/json"
+                        onchange=self.link.callback(|v: ChangeData| match v {
+                            ChangeData::Files(files) => Msg::ImportData(
+                                (0..files.length()).filter_map(|ii| files.get(ii)).collect()
+                            ),
+                            _ => Msg::Nope,
+                        })
+                    />
+                </div>
+            </div>
+        }
+    }
+
+    fn audit_button(&self, subject: Subject) -> Html {
+        html! {
+            <button onclick=self.link.callback(move |_| Msg::ShowAudit(subject))>
+                {"Audit Trail"}
+            </button>
+        }
+    }
+
+    // audit_trail shows the changes that concern the subject being audited,
+    // newest first. It can be read and exported, but not changed.
+    fn audit_trail(&self) -> Html {
+        let (subject, audit) = match &self.audit {
+            Some(audit) => audit,
+            None => return html! {},
+        };
+        let title = match *subject {
+            Subject::Tenant(id) => format!("Audit Trail: Tenant {}", self.state.tenant_name(id)),
+            Subject::Site(id) => format!("Audit Trail: Site {}", self.state.site_number(id)),
+            Subject::Lease(id) => format!("Audit Trail: Lease {}", id),
+            Subject::Business => "Audit Trail: Business".to_string(),
+        };
+        let json = |record| serde_json::to_string(record).unwrap_or_default();
+
+        html! {
+            <div class="row">
+                <div class="col">
+                    <div class="card">
+                        <h5 class="card-header">
+                            {title}
+                            <a class="close" onclick=self.link.callback(|_| Msg::HideAudit)>
+                                <i class="fa fa-close"/>
+                            </a>
+                        </h5>
+                        <div class="card-body">
+                            <list>
+                                {for audit.iter().rev().filter(|a| a.concerns(*subject, &self.state)).map(|a| html! {
+                                    <item class="side padded">
+                                        <p>{format!(
+                                            "{} by {}: {}",
+                                            a.at.map_or("Unknown time".to_string(), |at| at.with_timezone(&Local).format("%F %T").to_string()),
+                                            if a.actor.is_empty() { "unknown" } else { &a.actor },
+                                            a.description,
+                                        )}</p>
+                                        <table class="table">
+                                            <tr><th>{"Before"}</th><th>{"After"}</th></tr>
+                                            {for a.changes.iter().map(|c| html! {
+                                                <tr>
+                                                    <td><code>{c.before.as_ref().map(json).unwrap_or_default()}</code></td>
+                                                    <td><code>{c.after.as_ref().map(json).unwrap_or_default()}</code></td>
+                                                </tr>
+                                            })}
+                                        </table>
+                                    </item>
+                                })}
+                            </list>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn history_list(&self) -> Html {
+        html! {
+            <div class="card">
+                <h5 class="card-header">
+                    {"History"}
+                </h5>
+                <div class="card-body">
+                    <div class="side padded">
+                        <button
+                            disabled=self.history.done().is_empty()
+                            onclick=self.link.callback(|_| Msg::Undo)
+                        >
+                            {"Undo (Ctrl+Z)"}
+                        </button>
+                        <button
+                            disabled=self.history.undone().next().is_none()
+                            onclick=self.link.callback(|_| Msg::Redo)
+                        >
+                            {"Redo (Ctrl+Shift+Z)"}
+                        </button>
+                    </div>
+                    <list>
+                        {for self.history.done().iter().map(|step| html! {
+                            <item class="side padded">
+                                <p>{&step.description}</p>
+                            </item>
+                        })}
+                        {for self.history.undone().map(|step| html! {
+                            <item class="side padded undone">
+                                <p>{format!("Undone: {}", step.description)}</p>
+                            </item>
+                        })}
+                    </list>
+                </div>
+            </div>
+        }
+    }
+
     fn tenant_list(&self) -> Html {
+        let today = today();
+
         html! {
             <div class="card">
                 <h5 class="card-header">
@@ -261,7 +690,56 @@ impl App {
                         {for self.state.tenants.values().map(|t| html!{
                             <item class="side padded">
                                 <p>{format!("Name: {}", &t.name)}</p>
-                                <p>{format!("Contact: {}", &t.contact)}</p>
+                                {self.tenant_contact(&t.contact)}
+                                <p>{format!("Owing: {}", self.state.tenant_owing(t.id, today))}</p>
+                                {{
+                                    let arrears = self.state.tenant_arrears(t.id, today);
+                                    let ended: Money = arrears
+                                        .iter()
+                                        .filter(|a| !a.is_current())
+                                        .map(|a| a.total())
+                                        .sum();
+                                    html! {
+                                        <>
+                                        {match arrears.first() {
+                                            Some(oldest) => html! {
+                                                <p>{format!(
+                                                    "Arrears: {} on {} lease(s), oldest {} days",
+                                                    arrears.iter().map(|a| a.total()).sum::<Money>(),
+                                                    arrears.len(),
+                                                    oldest.days(today),
+                                                )}</p>
+                                            },
+                                            None => html! {},
+                                        }}
+                                        {if ended.is_zero() {
+                                            html! {}
+                                        } else {
+                                            html! { <p>{format!("Including {} from ended leases", ended)}</p> }
+                                        }}
+                                        </>
+                                    }
+                                }}
+                                {self.tenant_notifications(t.id)}
+                                {{
+                                    let at = t.id;
+                                    let taken = self
+                                        .state
+                                        .tenants
+                                        .values()
+                                        .filter(|other| other.id != at)
+                                        .map(|other| other.name.clone())
+                                        .collect::<Vec<_>>();
+                                    html! {
+                                        <RenameForm
+                                            label="New Name"
+                                            action="Rename"
+                                            taken=taken
+                                            submit=self.link.callback(move |v| Msg::RenameTenant(at, v))
+                                        />
+                                    }
+                                }}
+                                {self.audit_button(Subject::Tenant(t.id))}
                             </item>
                         })}
                     </list>
@@ -270,7 +748,193 @@ impl App {
         }
     }
 
+    fn tenant_contact(&self, contact: &Contact) -> Html {
+        let join = |items: Vec<String>| items.join(", ");
+
+        html! {
+            <>
+                {if contact.emails.is_empty() {
+                    html! {}
+                } else {
+                    html! { <p>{format!("Email: {}", join(contact.emails.iter().map(|e| e.to_string()).collect()))}</p> }
+                }}
+                {if contact.phones.is_empty() {
+                    html! {}
+                } else {
+                    html! { <p>{format!("Phone: {}", join(contact.phones.iter().map(|p| p.to_string()).collect()))}</p> }
+                }}
+                {if contact.address.is_empty() {
+                    html! {}
+                } else {
+                    html! { <p>{format!("Address: {}", join(contact.address.lines().map(String::from).collect()))}</p> }
+                }}
+                <p>{format!("Prefers: {}", contact.preferred)}</p>
+                {match &contact.emergency {
+                    Some(emergency) => html! { <p>{format!("Emergency: {}", emergency)}</p> },
+                    None => html! {},
+                }}
+                {if contact.notes.is_empty() {
+                    html! {}
+                } else {
+                    html! { <p>{format!("Notes: {}", &contact.notes)}</p> }
+                }}
+            </>
+        }
+    }
+
+    // tenant_notifications lists the reminders sent to a tenant, most recent
+    // first.
+    fn tenant_notifications(&self, tenant: TenantId) -> Html {
+        let mut notifications: Vec<_> = self.state.notifications_for(tenant).collect();
+        if notifications.is_empty() {
+            return html! {};
+        }
+        notifications.reverse();
+
+        html! {
+            <details>
+                <summary>{format!("Reminders ({})", notifications.len())}</summary>
+                <ul>
+                    {for notifications.into_iter().map(|n| html! {
+                        <li title=&n.body>{n}</li>
+                    })}
+                </ul>
+            </details>
+        }
+    }
+
+    // arrears_list is every lease with money owing, whether or not the lease
+    // is still running.
+    fn arrears_list(&self) -> Html {
+        let today = today();
+
+        html! {
+            <div class="card">
+                <h5 class="card-header">
+                    {"Arrears"}
+                </h5>
+                <div class="card-body">
+                    <table class="table">
+                        <thead>
+                            <tr>
+                                <th>{"Tenant"}</th>
+                                <th>{"Site"}</th>
+                                <th>{"Lease"}</th>
+                                <th>{"Rent"}</th>
+                                <th>{"Utilities"}</th>
+                                <th>{"Total"}</th>
+                                <th>{"Days"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {for self.state.arrears(today).into_iter().map(|a| {
+                                let at = a.lease;
+                                let start = self
+                                    .state
+                                    .lease(at)
+                                    .map(|l| l.term.start.to_string())
+                                    .unwrap_or_default();
+                                html! {
+                                    <tr>
+                                        <td>{&a.tenant_name}</td>
+                                        <td>{&a.site_number}</td>
+                                        <td>{format!("From {} ({})", start, &a.status)}</td>
+                                        <td>{a.rent}</td>
+                                        <td>{a.utilities}</td>
+                                        <td>{a.total()}</td>
+                                        <td>{a.days(today)}</td>
+                                        <td>
+                                            <button onclick=self.link.callback(move |_| Msg::SelectLease(Some(at)))>
+                                                {"Details"}
+                                            </button>
+                                        </td>
+                                    </tr>
+                                }
+                            })}
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+
+    fn invoice_list(&self) -> Html {
+        html! {
+            <div class="card">
+                <h5 class="card-header">
+                    {"Invoices"}
+                </h5>
+                <div class="card-body">
+                    <button onclick=self.link.callback(|_| Msg::IssueInvoices)>
+                        {"Issue Due Invoices"}
+                    </button>
+                    {self.invoice_table(self.state.invoices.iter().rev().collect())}
+                </div>
+            </div>
+        }
+    }
+
+    fn invoice_table(&self, invoices: Vec<&Invoice>) -> Html {
+        let today = today();
+
+        html! {
+            <table class="table">
+                <thead>
+                    <tr>
+                        <th>{"Invoice"}</th>
+                        <th>{"Tenant"}</th>
+                        <th>{"Site"}</th>
+                        <th>{"Period"}</th>
+                        <th>{"Due"}</th>
+                        <th>{"Previous Balance"}</th>
+                        <th>{"Charges"}</th>
+                        <th>{"Credit Applied"}</th>
+                        <th>{"Amount Due"}</th>
+                        <th>{"Status"}</th>
+                        <th>{"Delivery"}</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {for invoices.into_iter().map(|i| {
+                        let number = i.number;
+                        let site = self
+                            .state
+                            .lease(i.lease)
+                            .map_or("", |l| self.state.site_number(l.site));
+                        html! {
+                            <tr>
+                                <td>{format!("#{} issued {}", i.number, i.issued)}</td>
+                                <td>{self.state.tenant_name(i.tenant)}</td>
+                                <td>{site}</td>
+                                <td>{format!("{} to {}", i.from, i.to)}</td>
+                                <td>{i.due}</td>
+                                <td>{i.previous_balance}</td>
+                                <td>{i.total()}</td>
+                                <td>{i.credit_applied}</td>
+                                <td>{i.amount_due()}</td>
+                                <td>{self.state.invoice_status(i, today)}</td>
+                                <td>{match i.deliveries.last() {
+                                    Some(delivery) => delivery.to_string(),
+                                    None => "Not sent".to_string(),
+                                }}</td>
+                                <td>
+                                    <button onclick=self.link.callback(move |_| Msg::DownloadInvoice(number))>
+                                        {"PDF"}
+                                    </button>
+                                </td>
+                            </tr>
+                        }
+                    })}
+                </tbody>
+            </table>
+        }
+    }
+
     fn site_list(&self) -> Html {
+        let today = today();
+
         html! {
             <div class="card">
                 <h5 class="card-header">
@@ -282,18 +946,36 @@ impl App {
                             <item class="side padded">
                                 <p>{format!("Number: {}", &s.number)}</p>
                                 <p>{format!("Kind: {}", &s.kind)}</p>
-                                // <p>{format!("Lease: {:?}", &s.lease)}</p>
-                                // TODO: create lease.
-                                // - CreateLease (Site, Tenant, Start, Duration) -> Lease
-                                // {if s.lease.is_none() {
-                                //     html! {
-                                //         <button onclick=self.link.callback(|_| Msg::Nope)>
-                                //             {"Lease Me"}
-                                //         </button>
-                                //     }
-                                // } else {
-                                //     html!{}
-                                // }}
+                                {for s.meters.iter().map(|m| html! {
+                                    <p>{match m.readings.last() {
+                                        Some(r) => format!("Meter: {} last read {} {} on {}", m, r.value, m.utility.unit(), r.on),
+                                        None => format!("Meter: {} not yet read", m),
+                                    }}</p>
+                                })}
+                                {if s.is_vacant(today) {
+                                    html! { <span class="badge vacant">{"Vacant"}</span> }
+                                } else {
+                                    html! { <span class="badge occupied">{"Occupied"}</span> }
+                                }}
+                                {{
+                                    let at = s.id;
+                                    let taken = self
+                                        .state
+                                        .sites
+                                        .values()
+                                        .filter(|other| other.id != at)
+                                        .map(|other| other.number.clone())
+                                        .collect::<Vec<_>>();
+                                    html! {
+                                        <RenameForm
+                                            label="New Number"
+                                            action="Renumber"
+                                            taken=taken
+                                            submit=self.link.callback(move |v| Msg::RenumberSite(at, v))
+                                        />
+                                    }
+                                }}
+                                {self.audit_button(Subject::Site(s.id))}
                             </item>
                         })}
                     </list>
@@ -310,11 +992,34 @@ impl App {
                 </h5>
                 <div class="card-body">
                     <list>
-                        {for self.state.leases.iter().map(|l| html!{
+                        {for self.state.leases().map(|l| html!{
                             <item class="side padded">
-                                <p>{format!("Tenant: {}", &l.tenant_name)}</p>
-                                <p>{format!("Site: {}", &l.site_number)}</p>
-                                <p>{format!("{:?}", l.term)}</p>
+                                <p>{format!("Tenant: {}", self.state.tenant_name(l.tenant))}</p>
+                                <p>{format!("Site: {}", self.state.site_number(l.site))}</p>
+                                <p>{format!("Term: {} to {}", l.term.start, l.term.end())}</p>
+                                <p>{format!("Rent: {} per {}", l.term.rent, l.term.frequency.per())}</p>
+                                <p>{format!(
+                                    "Rent owing: {}, Utilities owing: {}",
+                                    self.state.owing(l.id, Towards::Rent, today()),
+                                    self.state.owing(l.id, Towards::Utilities, today()),
+                                )}</p>
+                                <p>{format!("Status: {}", &l.status)}</p>
+                                {match l.renews {
+                                    Some(id) => html! { <p>{format!("Renews lease {}", id)}</p> },
+                                    None => html! {},
+                                }}
+                                {for l.history.iter().map(|t| html! {
+                                    <p><small>{format!("{}: {} → {}", t.on, t.from, t.to)}</small></p>
+                                })}
+                                {self.lease_actions(l)}
+                                {{
+                                    let at = l.id;
+                                    html! {
+                                        <button onclick=self.link.callback(move |_| Msg::SelectLease(Some(at)))>
+                                            {"Details"}
+                                        </button>
+                                    }
+                                }}
                             </item>
                         })}
                     </list>
@@ -323,78 +1028,316 @@ impl App {
         }
     }
 
-    fn error(&mut self, msg: String) -> bool {
-        self.state.errors.push(msg);
-        self.storage.store(KEY, Json(&self.state));
-        true
+    // lease_detail shows the selected lease and its rent schedule.
+    fn lease_detail(&self) -> Html {
+        let lease = match self.selected_lease.and_then(|at| self.state.lease(at)) {
+            Some(lease) => lease,
+            None => return html! {},
+        };
+        let at = lease.id;
+        let to_date = std::cmp::min(today(), lease.end());
+        let charges = self.state.utility_charges(at, lease.term.start, to_date);
+        let utility_charges: Html = match charges {
+            Ok(lines) => lines
+                .iter()
+                .map(|c| {
+                    html! {
+                        <tr>
+                            <td>{c.utility}</td>
+                            <td>{&c.description}</td>
+                            <td>{c.amount}</td>
+                        </tr>
+                    }
+                })
+                .collect(),
+            Err(err) => html! {
+                <tr>
+                    <td colspan="3">{format!("Could not price usage: {}", err)}</td>
+                </tr>
+            },
+        };
+
+        html! {
+            <div class="row">
+                <div class="col">
+                    <div class="card">
+                        <h5 class="card-header">
+                            {format!("Lease: {}", lease)}
+                            <a
+                                class="close"
+                                onclick=self.link.callback(|_| Msg::SelectLease(None))
+                            >
+                                <i class="fa fa-close"/>
+                            </a>
+                        </h5>
+                        <div class="card-body padded">
+                            <p>{format!("Status: {}", &lease.status)}</p>
+                            <p>{format!("Rent: {} per {}", lease.term.rent, lease.term.frequency.per())}</p>
+                            <table class="table">
+                                <thead>
+                                    <tr>
+                                        <th>{"Due"}</th>
+                                        <th>{"Period"}</th>
+                                        <th>{"Amount"}</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {for lease.rent_schedule().iter().map(|c| html! {
+                                        <tr>
+                                            <td>{c.due}</td>
+                                            <td>{format!("{} to {}", c.from, c.to)}</td>
+                                            <td>{c.amount}</td>
+                                        </tr>
+                                    })}
+                                </tbody>
+                            </table>
+                            <h5>{"Utility Usage"}</h5>
+                            <table class="table">
+                                <thead>
+                                    <tr>
+                                        <th>{"Meter"}</th>
+                                        <th>{"Period"}</th>
+                                        <th>{"Usage"}</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {for self.state.lease_usage(lease.id).iter().map(|u| html! {
+                                        <tr>
+                                            <td>{format!("{} ({})", &u.meter, u.utility)}</td>
+                                            <td>{format!("{} to {}", u.from, u.to)}</td>
+                                            <td>{format!("{} {}", u.units, u.utility.unit())}</td>
+                                        </tr>
+                                    })}
+                                </tbody>
+                            </table>
+                            <h5>{format!("Utility Charges to {}", to_date)}</h5>
+                            <table class="table">
+                                <thead>
+                                    <tr>
+                                        <th>{"Utility"}</th>
+                                        <th>{"Item"}</th>
+                                        <th>{"Amount"}</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {utility_charges}
+                                </tbody>
+                            </table>
+                            <h5>{"Utility Bills"}</h5>
+                            <p>{format!("Owing: {}", self.state.owing(at, Towards::Utilities, today()))}</p>
+                            <table class="table">
+                                <thead>
+                                    <tr>
+                                        <th>{"Bill"}</th>
+                                        <th>{"Period"}</th>
+                                        <th>{"Total"}</th>
+                                        <th>{"Credit Applied"}</th>
+                                        <th>{"Due"}</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {for lease.utility.bills.iter().map(|b| html! {
+                                        <tr>
+                                            <td>{format!("#{} issued {}", b.number, b.issued)}</td>
+                                            <td>{format!("{} to {}", b.from, b.to)}</td>
+                                            <td>{b.total()}</td>
+                                            <td>{b.credit_applied}</td>
+                                            <td>{b.due()}</td>
+                                        </tr>
+                                    })}
+                                </tbody>
+                            </table>
+                            <BillForm
+                                submit=self.link.callback(move |v| Msg::IssueUtilityBill(at, v))
+                            />
+                            <h5>{"Invoices"}</h5>
+                            {self.lease_invoices(at)}
+                            <h5>{"Ledger"}</h5>
+                            <p>{format!("Bond held: {}", self.state.bond_held(at))}</p>
+                            {self.ledger_table(at)}
+                            <h5>{"Payments"}</h5>
+                            {for self.state.payments_for(at).map(|p| html! {
+                                <p><small>{p}</small></p>
+                            })}
+                        </div>
+                    </div>
+                </div>
+            </div>
+        }
     }
-}
 
-impl fmt::Display for SiteKind {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                SiteKind::Flat => "Flat",
-                SiteKind::Cabin => "Cabin",
-                SiteKind::House => "House",
-                SiteKind::Other(kind) => kind,
-            }
-        )
+    // lease_invoices lists the invoices issued on a lease, with a button to
+    // invoice the next period.
+    fn lease_invoices(&self, at: LeaseId) -> Html {
+        html! {
+            <>
+                <button onclick=self.link.callback(move |_| Msg::IssueInvoice(at))>
+                    {"Issue Next Invoice"}
+                </button>
+                <button onclick=self.link.callback(move |_| Msg::DownloadStatement(at))>
+                    {"Download Statement"}
+                </button>
+                {self.audit_button(Subject::Lease(at))}
+                {self.invoice_table(self.state.invoices_for(at).collect())}
+            </>
+        }
     }
-}
 
-impl From<&str> for SiteKind {
-    fn from(s: &str) -> Self {
-        let s = s.to_lowercase();
-        match s.as_str() {
-            "cabin" => SiteKind::Cabin,
-            "house" => SiteKind::House,
-            "flat" => SiteKind::Flat,
-            _ => SiteKind::Other(s),
+    // ledger_table lists the postings to a lease's accounts.
+    fn ledger_table(&self, at: LeaseId) -> Html {
+        html! {
+            <table class="table">
+                <thead>
+                    <tr>
+                        <th>{"Date"}</th>
+                        <th>{"Entry"}</th>
+                        <th>{"Debit"}</th>
+                        <th>{"Credit"}</th>
+                        <th>{"Amount"}</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {for self.state.ledger.lease_postings(at).map(|p| html! {
+                        <tr>
+                            <td>{p.on}</td>
+                            <td>{&p.source}</td>
+                            <td>{&p.debit}</td>
+                            <td>{&p.credit}</td>
+                            <td>{p.amount}</td>
+                        </tr>
+                    })}
+                </tbody>
+            </table>
         }
     }
-}
 
-impl From<String> for SiteKind {
-    fn from(s: String) -> Self {
-        let s = s.to_lowercase();
-        match s.as_str() {
-            "cabin" => SiteKind::Cabin,
-            "house" => SiteKind::House,
-            "flat" => SiteKind::Flat,
-            _ => SiteKind::Other(s),
+    // lease_actions are the lifecycle transitions available from the lease's
+    // current status.
+    fn lease_actions(&self, lease: &Lease) -> Html {
+        let at = lease.id;
+        match lease.status {
+            Status::Draft => html! {
+                <button onclick=self.link.callback(move |_| Msg::ActivateLease(at))>
+                    {"Activate"}
+                </button>
+            },
+            Status::Active => {
+                let expire = if today() > lease.term.end() {
+                    html! {
+                        <button onclick=self.link.callback(move |_| Msg::ExpireLease(at))>
+                            {"Expire"}
+                        </button>
+                    }
+                } else {
+                    html! {}
+                };
+                html! {
+                    <>
+                        {expire}
+                        <RenewForm
+                            submit=self.link.callback(move |v| Msg::RenewLease(at, v))
+                        />
+                        <TerminateForm
+                            submit=self.link.callback(move |v| Msg::TerminateLease(at, v))
+                        />
+                    </>
+                }
+            }
+            Status::Expired => html! {
+                <RenewForm
+                    submit=self.link.callback(move |v| Msg::RenewLease(at, v))
+                />
+            },
+            _ => html! {},
         }
     }
-}
 
-impl From<site_form::Kind> for SiteKind {
-    fn from(kind: site_form::Kind) -> Self {
-        use site_form::Kind::*;
-        match kind {
-            Cabin => SiteKind::Cabin,
-            House => SiteKind::House,
-            Flat => SiteKind::Flat,
-            Other(v) => SiteKind::Other(v),
+    // record makes the change an event records and logs it, posting any
+    // rent that has fallen due. If the change fails or cannot be logged, the
+    // state is left as it was.
+    fn record(&mut self, event: Event) -> Result<(), Box<dyn Error>> {
+        let mut store = self.store.borrow_mut();
+        self.history
+            .record(&mut *store, &mut self.state, event, &self.actor)?;
+        let due = Event::RentFellDue { on: today() };
+        store.record(&mut self.state, due, &self.actor)?;
+        Ok(())
+    }
+
+    // reload reads state back from the store once saving to it has failed,
+    // since the store may have taken earlier changes back out too. Steps made
+    // before then can no longer be undone.
+    fn reload(&mut self, failure: String) -> bool {
+        let loaded = self.store.borrow_mut().load();
+        match loaded {
+            Ok(state) => {
+                self.state = state.unwrap_or_default();
+                self.history = History::default();
+                self.error(format!(
+                    "Could not save: {}. Saved data has been reloaded.",
+                    failure
+                ))
+            }
+            Err(err) => self.error(format!(
+                "Could not save: {}. Saved data could not be reloaded: {}",
+                failure, err
+            )),
         }
     }
-}
 
-impl fmt::Display for Site {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", &self.number)
+    fn error(&mut self, msg: String) -> bool {
+        self.errors.push(msg);
+        true
     }
-}
 
-impl fmt::Display for Tenant {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", &self.name)
+    fn field_errors(&mut self, errors: HashMap<String, String>) -> bool {
+        for (field, err) in errors {
+            self.errors.push(format!("{}: {}", field, err));
+        }
+        true
     }
 }
 
-impl Default for SiteKind {
-    fn default() -> Self {
-        SiteKind::Cabin
+fn today() -> Date {
+    Local::now().date_naive()
+}
+
+// shortcut maps Ctrl+Z (or Cmd+Z) to undo, and Ctrl+Shift+Z and Ctrl+Y to
+// redo. Keys typed into a field are left to the field's own undo.
+fn shortcut(e: KeyboardEvent) -> Msg {
+    let in_field = e
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .is_some_and(|el| matches!(el.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"));
+    if in_field || !(e.ctrl_key() || e.meta_key()) {
+        return Msg::Nope;
     }
+    let msg = match e.key().to_lowercase().as_str() {
+        "z" if e.shift_key() => Msg::Redo,
+        "z" => Msg::Undo,
+        "y" => Msg::Redo,
+        _ => return Msg::Nope,
+    };
+    e.prevent_default();
+    msg
+}
+
+const PDF: &str = "application/pdf";
+const JSON: &str = "application/json";
+
+// download hands a file to the browser to save.
+fn download(filename: &str, content_type: &str, bytes: &[u8]) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = BlobPropertyBag::new();
+    options.set_type(content_type);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    Url::revoke_object_url(&url)
 }
@@ -0,0 +1,95 @@
+use crate::form;
+use std::collections::HashMap;
+use std::mem;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+/// Form collects the last day to bill utilities up to.
+pub struct Form {
+    pub link: ComponentLink<Self>,
+    pub props: Props,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Model {
+    pub to: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub submit: Callback<Model>,
+}
+
+pub enum Msg {
+    Edit(String),
+    Submit,
+}
+
+impl Component for Form {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(v) => {
+                self.model.to = v;
+                self.errors.clear();
+            }
+            Msg::Submit => {
+                if self.model.to.parse::<chrono::NaiveDate>().is_err() {
+                    self.errors.insert("to".into(), "must be a date".into());
+                } else {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Bill Up To"}
+                    error=get_error("to")
+                >
+                    <input
+                        type="date"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(v.value))
+                        value=&self.model.to
+                    />
+                </form::Field>
+
+                <button type="submit">
+                    {"Issue Utility Bill"}
+                </button>
+            </form>
+        }
+    }
+}
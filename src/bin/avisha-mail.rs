@@ -0,0 +1,73 @@
+//! avisha-mail emails invoices that have not yet been delivered, reading the
+//! state exported from the app and writing it back with each delivery
+//! recorded, ready to import again.
+//!
+//! Usage:
+//!
+//!     avisha-mail <state.json> --smtp <host>
+//!     avisha-mail <state.json> --smtp-plain <host:port>
+//!     avisha-mail <state.json> --outbox <dir>
+//!
+//! `--smtp` uses STARTTLS on the submission port, logging in with
+//! `SMTP_USERNAME` and `SMTP_PASSWORD` when they are set. `--smtp-plain` is for
+//! a relay on a trusted network. `--outbox` writes `.eml` files instead of
+//! sending anything.
+
+use avisha::domain::DeliveryStatus;
+use avisha::mail::{self, file::File, smtp::Smtp, Transport};
+use avisha::store;
+use chrono::Local;
+use std::{env, fs, process};
+
+const USAGE: &str =
+    "usage: avisha-mail <state.json> (--smtp <host> | --smtp-plain <host:port> | --outbox <dir>)";
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("avisha-mail: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let (path, flag, value) = match args.as_slice() {
+        [path, flag, value] => (path, flag.as_str(), value),
+        _ => return Err(USAGE.into()),
+    };
+    let mut transport: Box<dyn Transport> = match flag {
+        "--smtp" => {
+            let credentials = match (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+                (Ok(username), Ok(password)) => Some((username, password)),
+                _ => None,
+            };
+            Box::new(Smtp::new(value, credentials).map_err(|err| err.to_string())?)
+        }
+        "--smtp-plain" => {
+            let (host, port) = value
+                .rsplit_once(':')
+                .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+                .ok_or_else(|| format!("{:?} is not host:port", value))?;
+            Box::new(Smtp::plain(host, port))
+        }
+        "--outbox" => Box::new(File::new(value).map_err(|err| err.to_string())?),
+        _ => return Err(USAGE.into()),
+    };
+
+    let data = fs::read_to_string(path).map_err(|err| format!("reading {}: {}", path, err))?;
+    let mut state = store::from_str(&data).map_err(|err| format!("reading {}: {}", path, err))?;
+
+    let outcomes =
+        mail::deliver_invoices(&mut state, transport.as_mut(), Local::now().date_naive());
+    for (number, status) in &outcomes {
+        match status {
+            DeliveryStatus::Sent => println!("invoice #{}: sent", number),
+            DeliveryStatus::Failed(reason) => println!("invoice #{}: failed: {}", number, reason),
+        }
+    }
+    if outcomes.is_empty() {
+        println!("no invoices to send");
+    }
+
+    let data = store::to_string(&state).map_err(|err| err.to_string())?;
+    fs::write(path, data).map_err(|err| format!("writing {}: {}", path, err))
+}
@@ -0,0 +1,68 @@
+//! avisha-sms texts tenants reminders of rent falling due and money overdue,
+//! reading the state exported from the app and writing it back with each
+//! reminder logged against its tenant, ready to import again.
+//!
+//! Usage:
+//!
+//!     avisha-sms <state.json> --gateway <url>
+//!     avisha-sms --mock <address>
+//!
+//! `--gateway` posts messages to the provider's HTTP endpoint. `--mock` serves
+//! a stand-in for a provider at `address`, such as `127.0.0.1:8025`, printing
+//! each message instead of sending it; point `--gateway` at
+//! `http://127.0.0.1:8025/messages` to try reminders out.
+
+use avisha::domain::DeliveryStatus;
+use avisha::sms::{self, http::Http, mock};
+use avisha::store;
+use chrono::Local;
+use std::net::TcpListener;
+use std::{env, fs, process};
+
+const USAGE: &str = "usage: avisha-sms <state.json> --gateway <url> | avisha-sms --mock <address>";
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("avisha-sms: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    match args.as_slice() {
+        [flag, address] if flag == "--mock" => serve(address),
+        [path, flag, url] if flag == "--gateway" => remind(path, url),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn serve(address: &str) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|err| format!("{}: {}", address, err))?;
+    println!("serving mock SMS gateway at http://{}/messages", address);
+    mock::serve(listener, |message| {
+        println!("to {}: {}", &message.to, &message.body)
+    })
+    .map_err(|err| err.to_string())
+}
+
+fn remind(path: &str, url: &str) -> Result<(), String> {
+    let mut gateway = Http::new(url).map_err(|err| err.to_string())?;
+
+    let data = fs::read_to_string(path).map_err(|err| format!("reading {}: {}", path, err))?;
+    let mut state = store::from_str(&data).map_err(|err| format!("reading {}: {}", path, err))?;
+
+    let sent = sms::send_reminders(&mut state, &mut gateway, Local::now().date_naive());
+    for notification in &sent {
+        let tenant_name = state.tenant_name(notification.tenant);
+        match &notification.status {
+            DeliveryStatus::Sent => println!("{}: sent", tenant_name),
+            DeliveryStatus::Failed(reason) => println!("{}: failed: {}", tenant_name, reason),
+        }
+    }
+    if sent.is_empty() {
+        println!("no reminders to send");
+    }
+
+    let data = store::to_string(&state).map_err(|err| err.to_string())?;
+    fs::write(path, data).map_err(|err| format!("writing {}: {}", path, err))
+}
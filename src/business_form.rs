@@ -0,0 +1,192 @@
+use crate::domain::Business;
+use crate::form;
+use std::collections::HashMap;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+/// Form edits the business details printed on invoices and statements.
+pub struct Form {
+    pub link: ComponentLink<Self>,
+    pub props: Props,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Model {
+    pub name: String,
+    pub address: String,
+    pub phone: String,
+    pub email: String,
+    pub payment_instructions: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub submit: Callback<Model>,
+    /// The details currently on record, which the form starts from.
+    pub business: Business,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    Name(String),
+    Address(String),
+    Phone(String),
+    Email(String),
+    PaymentInstructions(String),
+}
+
+impl Component for Form {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let model = Model::from(props.business.clone());
+        Form {
+            props,
+            link,
+            model,
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::Name(v) => self.model.name = v,
+                    Field::Address(v) => self.model.address = v,
+                    Field::Phone(v) => self.model.phone = v,
+                    Field::Email(v) => self.model.email = v,
+                    Field::PaymentInstructions(v) => self.model.payment_instructions = v,
+                };
+                self.errors.clear();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.errors.is_empty() {
+                    self.props.submit.emit(self.model.clone());
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Name"}
+                    error=get_error("name")
+                >
+                    <input
+                        type="text"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Name(v.value)))
+                        value=&self.model.name
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Address"}
+                    error=get_error("address")
+                >
+                    <textarea
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Address(v.value)))
+                        value=&self.model.address
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Phone"}
+                    error=get_error("phone")
+                >
+                    <input
+                        type="tel"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Phone(v.value)))
+                        value=&self.model.phone
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Email"}
+                    error=get_error("email")
+                >
+                    <input
+                        type="email"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Email(v.value)))
+                        value=&self.model.email
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Payment Instructions"}
+                    error=get_error("payment_instructions")
+                >
+                    <textarea
+                        placeholder="Bank account details"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::PaymentInstructions(v.value)))
+                        value=&self.model.payment_instructions
+                    />
+                </form::Field>
+
+                <button type="submit">
+                    {"Save"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl Form {
+    fn validate(&mut self) {
+        self.errors.clear();
+
+        if self.model.name.trim().is_empty() {
+            self.errors.insert("name".into(), "must be non-zero".into());
+        }
+    }
+}
+
+impl From<Business> for Model {
+    fn from(b: Business) -> Self {
+        Model {
+            name: b.name,
+            address: b.address,
+            phone: b.phone,
+            email: b.email,
+            payment_instructions: b.payment_instructions,
+        }
+    }
+}
+
+impl From<Model> for Business {
+    fn from(m: Model) -> Self {
+        Business {
+            name: m.name,
+            address: m.address,
+            phone: m.phone,
+            email: m.email,
+            payment_instructions: m.payment_instructions,
+        }
+    }
+}
@@ -0,0 +1,1000 @@
+//! Domain types and the commands that change them.
+//!
+//! Nothing in here knows about yew or the browser: the UI is a thin client
+//! that issues commands against `State` and renders the result.
+
+use chrono::{Duration, NaiveDate as Date};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+pub mod arrears;
+pub mod audit;
+pub mod billing;
+pub mod contact;
+pub mod event;
+pub mod invoice;
+pub mod ledger;
+pub mod money;
+pub mod notification;
+pub mod payment;
+pub mod rent;
+pub mod tariff;
+pub mod utility;
+
+pub use arrears::Arrears;
+pub use audit::{Audited, Change, Record, Subject};
+pub use billing::{UtilityAccount, UtilityBill};
+pub use contact::{Contact, EmergencyContact, Email, Phone};
+pub use event::Event;
+pub use invoice::{Delivery, DeliveryStatus, Invoice, InvoiceStatus};
+pub use ledger::{Account, Ledger, Posting};
+pub use money::Money;
+pub use notification::{Channel, Notification, Reminder};
+pub use payment::{Method, Payment, Towards};
+pub use rent::Frequency;
+pub use tariff::{Rate, Tariff};
+pub use utility::{Meter, Reading, Utility};
+
+// Days is a duration in days.
+pub type Days = u32;
+
+/// MAX_TERM is the longest a lease term can run, in days: a hundred years.
+pub const MAX_TERM: Days = 36_525;
+
+/// TenantId identifies a tenant, whatever they are called.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default,
+)]
+pub struct TenantId(pub u32);
+
+/// SiteId identifies a site, however it is numbered.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default,
+)]
+pub struct SiteId(pub u32);
+
+/// LeaseId identifies a lease.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default,
+)]
+pub struct LeaseId(pub u32);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
+pub struct Tenant {
+    /// Assigned when the tenant is registered.
+    #[serde(default)]
+    pub id: TenantId,
+    pub name: String,
+    pub contact: Contact,
+}
+
+/// Business is who invoices and statements are issued by.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
+pub struct Business {
+    pub name: String,
+    /// Postal address, one line per line of text.
+    pub address: String,
+    pub phone: String,
+    pub email: String,
+    /// How tenants should pay, such as bank account details.
+    pub payment_instructions: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
+pub struct Site {
+    /// Assigned when the site is listed.
+    #[serde(default)]
+    pub id: SiteId,
+    pub number: String,
+    pub kind: SiteKind,
+    /// Every lease ever made on this site, ordered by start date.
+    #[serde(default)]
+    pub leases: Vec<Lease>,
+    #[serde(default)]
+    pub meters: Vec<Meter>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
+pub enum SiteKind {
+    #[default]
+    Cabin,
+    Flat,
+    House,
+    Other(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
+pub struct Lease {
+    /// Assigned when the lease is entered.
+    #[serde(default)]
+    pub id: LeaseId,
+    pub tenant: TenantId,
+    pub site: SiteId,
+    pub term: Term,
+    /// Leases saved before statuses existed were already in force.
+    #[serde(default = "Status::active")]
+    pub status: Status,
+    /// Every status change, oldest first.
+    #[serde(default)]
+    pub history: Vec<Transition>,
+    /// The lease on the same site that this one continues.
+    #[serde(default)]
+    pub renews: Option<LeaseId>,
+    #[serde(default)]
+    pub utility: UtilityAccount,
+}
+
+/// Status is where a lease stands in its lifecycle. A draft becomes active,
+/// and an active lease either expires, is terminated, or is renewed; an
+/// expired lease can still be renewed.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
+pub enum Status {
+    /// Entered but not yet in force.
+    #[default]
+    Draft,
+    Active,
+    /// The term ran its full course.
+    Expired,
+    /// Ended before the term ran out; `on` is the last day of occupancy.
+    Terminated {
+        on: Date,
+        reason: String,
+    },
+    /// Continued by a successor lease on the same site starting on `successor`.
+    Renewed {
+        successor: Date,
+    },
+}
+
+/// Transition records a change of status on a given day.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
+pub struct Transition {
+    pub on: Date,
+    pub from: Status,
+    pub to: Status,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
+pub struct Term {
+    pub start: Date,
+    pub duration: Days,
+    pub rent: Money,
+    #[serde(default)]
+    pub frequency: Frequency,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(try_from = "StoredState")]
+pub struct State {
+    pub tenants: HashMap<TenantId, Tenant>,
+    pub sites: HashMap<SiteId, Site>,
+    pub tariffs: Vec<Tariff>,
+    pub payments: Vec<Payment>,
+    /// Every charge, payment and credit, as postings between accounts.
+    pub ledger: Ledger,
+    /// Invoices in the order issued.
+    pub invoices: Vec<Invoice>,
+    pub business: Business,
+    /// Reminders and other messages sent to tenants.
+    pub notifications: Vec<Notification>,
+    // The ids to issue next. They only go up, so an id is never reused,
+    // even once the tenant, site or lease it was issued to has been undone.
+    next_tenant: u32,
+    next_site: u32,
+    next_lease: u32,
+}
+
+// StoredState accepts state saved before the ledger, once `store` has
+// migrated it to the current shape. State whose payments and bills cannot
+// be posted is refused rather than read with some of them missing.
+#[derive(Deserialize)]
+struct StoredState {
+    tenants: HashMap<TenantId, Tenant>,
+    sites: HashMap<SiteId, Site>,
+    #[serde(default)]
+    tariffs: Vec<Tariff>,
+    #[serde(default)]
+    payments: Vec<Payment>,
+    #[serde(default)]
+    ledger: Ledger,
+    #[serde(default)]
+    invoices: Vec<Invoice>,
+    #[serde(default)]
+    business: Business,
+    #[serde(default)]
+    notifications: Vec<Notification>,
+    #[serde(default)]
+    next_tenant: u32,
+    #[serde(default)]
+    next_site: u32,
+    #[serde(default)]
+    next_lease: u32,
+}
+
+/// Error is returned when a command would leave `State` inconsistent.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    DuplicateTenant(String),
+    DuplicateSite(String),
+    UnknownTenant(TenantId),
+    UnknownSite(SiteId),
+    UnknownLease(LeaseId),
+    EmptyTerm,
+    /// TermTooLong is a term longer than `MAX_TERM` or running past the last
+    /// day a date can hold.
+    TermTooLong,
+    /// LeaseConflict carries the existing lease whose term overlaps.
+    LeaseConflict(Box<Lease>),
+    IllegalTransition {
+        from: Status,
+        to: Status,
+    },
+    /// OutsideTerm is a lifecycle date that does not fit the lease term.
+    OutsideTerm(Date),
+    DuplicateMeter(String),
+    UnknownMeter(String),
+    /// ReadingOutOfOrder carries the latest reading, which a new reading must
+    /// come after and not be less than.
+    ReadingOutOfOrder(Reading),
+    InvalidTariff(String),
+    InvalidAmount(Money),
+    /// Overflow is a sum of money too large to hold.
+    Overflow,
+    /// NotTenant is a tenant acting on a lease that is not theirs.
+    NotTenant(TenantId),
+    /// NothingToInvoice is a lease whose every rent period has been invoiced.
+    NothingToInvoice(LeaseId),
+    /// NotStarted is a draft lease, which cannot be invoiced.
+    NotStarted(LeaseId),
+    UnknownInvoice(u32),
+}
+
+impl State {
+    /// register_tenant adds a tenant under a new id, rejecting a name that is
+    /// already taken.
+    pub fn register_tenant(&mut self, mut tenant: Tenant) -> Result<TenantId, Error> {
+        if self.tenant_named(&tenant.name).is_some() {
+            return Err(Error::DuplicateTenant(tenant.name));
+        }
+        tenant.id = TenantId(issue(&mut self.next_tenant));
+        let id = tenant.id;
+        self.tenants.insert(id, tenant);
+        Ok(id)
+    }
+
+    /// rename_tenant changes a tenant's name, rejecting one that is already
+    /// taken. Leases and payments refer to the tenant by id so are unaffected.
+    pub fn rename_tenant(&mut self, id: TenantId, name: String) -> Result<(), Error> {
+        if self.tenant_named(&name).is_some_and(|t| t.id != id) {
+            return Err(Error::DuplicateTenant(name));
+        }
+        self.tenants
+            .get_mut(&id)
+            .ok_or(Error::UnknownTenant(id))?
+            .name = name;
+        Ok(())
+    }
+
+    /// list_site adds a site under a new id, rejecting a number that is
+    /// already taken.
+    pub fn list_site(&mut self, mut site: Site) -> Result<SiteId, Error> {
+        if self.site_numbered(&site.number).is_some() {
+            return Err(Error::DuplicateSite(site.number));
+        }
+        site.id = SiteId(issue(&mut self.next_site));
+        let id = site.id;
+        for lease in &mut site.leases {
+            lease.site = id;
+        }
+        self.sites.insert(id, site);
+        Ok(id)
+    }
+
+    /// renumber_site changes a site's number, rejecting one that is already
+    /// taken.
+    pub fn renumber_site(&mut self, id: SiteId, number: String) -> Result<(), Error> {
+        if self.site_numbered(&number).is_some_and(|s| s.id != id) {
+            return Err(Error::DuplicateSite(number));
+        }
+        self.sites
+            .get_mut(&id)
+            .ok_or(Error::UnknownSite(id))?
+            .number = number;
+        Ok(())
+    }
+
+    pub fn tenant(&self, id: TenantId) -> Option<&Tenant> {
+        self.tenants.get(&id)
+    }
+
+    pub fn site(&self, id: SiteId) -> Option<&Site> {
+        self.sites.get(&id)
+    }
+
+    pub fn tenant_named(&self, name: &str) -> Option<&Tenant> {
+        self.tenants.values().find(|t| t.name == name)
+    }
+
+    pub fn site_numbered(&self, number: &str) -> Option<&Site> {
+        self.sites.values().find(|s| s.number == number)
+    }
+
+    /// tenant_name is the name of a tenant, or empty if there is no such
+    /// tenant.
+    pub fn tenant_name(&self, id: TenantId) -> &str {
+        self.tenant(id).map_or("", |t| t.name.as_str())
+    }
+
+    /// site_number is the number of a site, or empty if there is no such
+    /// site.
+    pub fn site_number(&self, id: SiteId) -> &str {
+        self.site(id).map_or("", |s| s.number.as_str())
+    }
+
+    /// lease_site records a lease of an existing site to an existing tenant
+    /// under a new id.
+    pub fn lease_site(&mut self, mut lease: Lease) -> Result<LeaseId, Error> {
+        if !self.tenants.contains_key(&lease.tenant) {
+            return Err(Error::UnknownTenant(lease.tenant));
+        }
+        if !self.sites.contains_key(&lease.site) {
+            return Err(Error::UnknownSite(lease.site));
+        }
+        lease.term.check()?;
+        if let Some(existing) = self.conflicting_lease(&lease) {
+            return Err(Error::LeaseConflict(Box::new(existing.clone())));
+        }
+        lease.id = LeaseId(issue(&mut self.next_lease));
+        let id = lease.id;
+        if let Some(site) = self.sites.get_mut(&lease.site) {
+            site.insert_lease(lease);
+        }
+        Ok(id)
+    }
+
+    /// conflicting_lease finds an existing lease on the same site whose term
+    /// overlaps that of `lease`.
+    pub fn conflicting_lease(&self, lease: &Lease) -> Option<&Lease> {
+        self.sites.get(&lease.site)?.conflicting_lease(&lease.term)
+    }
+
+    /// lease finds the lease with the given id.
+    pub fn lease(&self, id: LeaseId) -> Option<&Lease> {
+        self.leases().find(|l| l.id == id)
+    }
+
+    /// activate_lease puts a draft lease into force.
+    pub fn activate_lease(&mut self, id: LeaseId, on: Date) -> Result<(), Error> {
+        self.lease_mut(id)?.transition(Status::Active, on)
+    }
+
+    /// expire_lease marks an active lease as having run its full term.
+    pub fn expire_lease(&mut self, id: LeaseId, on: Date) -> Result<(), Error> {
+        let lease = self.lease_mut(id)?;
+        if on <= lease.term.end() {
+            return Err(Error::OutsideTerm(on));
+        }
+        lease.transition(Status::Expired, on)
+    }
+
+    /// terminate_lease ends an active lease early, `end` being the last day
+    /// of occupancy.
+    pub fn terminate_lease(
+        &mut self,
+        id: LeaseId,
+        end: Date,
+        reason: String,
+        on: Date,
+    ) -> Result<(), Error> {
+        let lease = self.lease_mut(id)?;
+        if !lease.term.covers(end) {
+            return Err(Error::OutsideTerm(end));
+        }
+        lease.transition(Status::Terminated { on: end, reason }, on)
+    }
+
+    /// renew_lease continues a lease with a follow-on term for the same tenant
+    /// starting the day after it ends, keeping the rent unless a new one is
+    /// given. The successor is active immediately and both leases are linked.
+    pub fn renew_lease(
+        &mut self,
+        id: LeaseId,
+        duration: Days,
+        rent: Option<Money>,
+        on: Date,
+    ) -> Result<LeaseId, Error> {
+        let mut renewed = self.lease_mut(id)?.clone();
+        let mut successor = Lease {
+            id: LeaseId::default(),
+            tenant: renewed.tenant,
+            site: renewed.site,
+            term: Term {
+                start: renewed.end().succ_opt().ok_or(Error::TermTooLong)?,
+                duration,
+                rent: rent.unwrap_or(renewed.term.rent),
+                frequency: renewed.term.frequency,
+            },
+            status: Status::Draft,
+            history: vec![],
+            renews: Some(id),
+            utility: UtilityAccount::default(),
+        };
+        renewed.transition(
+            Status::Renewed {
+                successor: successor.term.start,
+            },
+            on,
+        )?;
+        successor.transition(Status::Active, on)?;
+
+        let successor_id = self.lease_site(successor)?;
+        *self.lease_mut(id)? = renewed;
+        Ok(successor_id)
+    }
+
+    fn lease_mut(&mut self, id: LeaseId) -> Result<&mut Lease, Error> {
+        self.sites
+            .values_mut()
+            .flat_map(|s| s.leases.iter_mut())
+            .find(|l| l.id == id)
+            .ok_or(Error::UnknownLease(id))
+    }
+
+    /// leases iterates the leases of every site.
+    pub fn leases(&self) -> impl Iterator<Item = &Lease> {
+        self.sites.values().flat_map(|s| s.leases.iter())
+    }
+
+    /// vacant_sites are the sites with no active lease on the given day.
+    pub fn vacant_sites(&self, on: Date) -> impl Iterator<Item = &Site> {
+        self.sites.values().filter(move |s| s.is_vacant(on))
+    }
+
+    /// free_sites are the sites a lease with the given term can be entered
+    /// on: those with no lease, draft or not, on any day of the term.
+    pub fn free_sites<'a>(&'a self, term: &'a Term) -> impl Iterator<Item = &'a Site> {
+        self.sites
+            .values()
+            .filter(move |s| s.conflicting_lease(term).is_none())
+    }
+}
+
+impl Site {
+    /// active_lease is the lease whose term covers the given day, if any.
+    pub fn active_lease(&self, on: Date) -> Option<&Lease> {
+        self.leases.iter().find(|l| l.is_active(on))
+    }
+
+    /// is_vacant reports whether the site can be leased on the given day.
+    pub fn is_vacant(&self, on: Date) -> bool {
+        self.active_lease(on).is_none()
+    }
+
+    /// conflicting_lease finds a lease on the site, draft or not, that
+    /// occupies it on a day of `term`.
+    pub fn conflicting_lease(&self, term: &Term) -> Option<&Lease> {
+        self.leases
+            .iter()
+            .find(|l| l.term.start <= term.end() && term.start <= l.end())
+    }
+
+    fn insert_lease(&mut self, lease: Lease) {
+        let at = self
+            .leases
+            .iter()
+            .position(|l| l.term.start > lease.term.start)
+            .unwrap_or(self.leases.len());
+        self.leases.insert(at, lease);
+    }
+}
+
+impl Lease {
+    /// end is the last day of occupancy, which is earlier than the end of
+    /// the term if the lease was terminated.
+    pub fn end(&self) -> Date {
+        match &self.status {
+            Status::Terminated { on, .. } => *on,
+            _ => self.term.end(),
+        }
+    }
+
+    /// is_active reports whether the lease is in force on the given day.
+    pub fn is_active(&self, on: Date) -> bool {
+        self.status != Status::Draft && self.term.start <= on && on <= self.end()
+    }
+
+    /// overlaps reports whether the two leases occupy the site on a common day.
+    pub fn overlaps(&self, other: &Lease) -> bool {
+        self.term.start <= other.end() && other.term.start <= self.end()
+    }
+
+    /// transition moves the lease to a new status, keeping the old one in
+    /// `history`.
+    pub fn transition(&mut self, to: Status, on: Date) -> Result<(), Error> {
+        let legal = matches!(
+            (&self.status, &to),
+            (Status::Draft, Status::Active)
+                | (Status::Active, Status::Expired)
+                | (Status::Active, Status::Terminated { .. })
+                | (Status::Active, Status::Renewed { .. })
+                | (Status::Expired, Status::Renewed { .. })
+        );
+        if !legal {
+            return Err(Error::IllegalTransition {
+                from: self.status.clone(),
+                to,
+            });
+        }
+        let from = std::mem::replace(&mut self.status, to.clone());
+        self.history.push(Transition { on, from, to });
+        Ok(())
+    }
+}
+
+impl Status {
+    fn active() -> Self {
+        Status::Active
+    }
+
+    /// is_started reports whether the lease has ever been in force.
+    pub fn is_started(&self) -> bool {
+        !matches!(self, Status::Draft)
+    }
+}
+
+impl Term {
+    /// end is the last day covered by the term. A term running past the last
+    /// day a date can hold ends on that day; `check` rejects such terms.
+    pub fn end(&self) -> Date {
+        self.checked_end().unwrap_or(Date::MAX)
+    }
+
+    /// checked_end is the last day covered by the term, failing if that is
+    /// past the last day a date can hold.
+    pub fn checked_end(&self) -> Result<Date, Error> {
+        self.start
+            .checked_add_signed(Duration::days(i64::from(self.duration)))
+            .and_then(|end| end.pred_opt())
+            .ok_or(Error::TermTooLong)
+    }
+
+    /// check rejects a term of no days or one too long to lease.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.duration == 0 {
+            return Err(Error::EmptyTerm);
+        }
+        if self.duration > MAX_TERM {
+            return Err(Error::TermTooLong);
+        }
+        self.checked_end().map(|_| ())
+    }
+
+    /// covers reports whether the given day falls within the term.
+    pub fn covers(&self, on: Date) -> bool {
+        self.start <= on && on <= self.end()
+    }
+
+    /// overlaps reports whether the two terms share at least one day.
+    pub fn overlaps(&self, other: &Term) -> bool {
+        self.start <= other.end() && other.start <= self.end()
+    }
+}
+
+// issue takes the next id from a counter.
+fn issue(next: &mut u32) -> u32 {
+    let id = *next;
+    *next += 1;
+    id
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            tenants: HashMap::new(),
+            sites: HashMap::new(),
+            tariffs: vec![],
+            payments: vec![],
+            ledger: Ledger::default(),
+            invoices: vec![],
+            business: Business::default(),
+            notifications: vec![],
+            next_tenant: 1,
+            next_site: 1,
+            next_lease: 1,
+        }
+    }
+}
+
+impl TryFrom<StoredState> for State {
+    type Error = Error;
+
+    fn try_from(stored: StoredState) -> Result<Self, Error> {
+        let StoredState {
+            tenants,
+            sites,
+            tariffs,
+            payments,
+            ledger,
+            invoices,
+            business,
+            notifications,
+            next_tenant,
+            next_site,
+            next_lease,
+        } = stored;
+        let mut state = State {
+            tenants,
+            sites,
+            tariffs,
+            payments,
+            ledger,
+            invoices,
+            business,
+            notifications,
+            next_tenant,
+            next_site,
+            next_lease,
+        };
+        // State saved before the counters issues ids after the highest in
+        // use.
+        let last_tenant = state.tenants.keys().map(|id| id.0).max().unwrap_or(0);
+        let last_site = state.sites.keys().map(|id| id.0).max().unwrap_or(0);
+        let last_lease = state.leases().map(|l| l.id.0).max().unwrap_or(0);
+        state.next_tenant = state.next_tenant.max(last_tenant + 1);
+        state.next_site = state.next_site.max(last_site + 1);
+        state.next_lease = state.next_lease.max(last_lease + 1);
+        // State saved before the ledger has its payments and bills posted
+        // now; rent is posted as it falls due.
+        if state.ledger.postings().is_empty() {
+            let mut postings: Vec<Posting> = state.payments.iter().map(|p| p.posting()).collect();
+            for lease in state.leases() {
+                postings.extend(
+                    lease
+                        .utility
+                        .bills
+                        .iter()
+                        .filter_map(|b| b.posting(lease.id)),
+                );
+            }
+            postings.sort_by_key(|p| p.on);
+            for posting in postings {
+                state.ledger.post(posting)?;
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DuplicateTenant(name) => write!(f, "tenant {:?} already registered", name),
+            Error::DuplicateSite(number) => write!(f, "site {:?} already listed", number),
+            Error::UnknownTenant(id) => write!(f, "no tenant {}", id),
+            Error::UnknownSite(id) => write!(f, "no site {}", id),
+            Error::UnknownLease(id) => write!(f, "no lease {}", id),
+            Error::EmptyTerm => write!(f, "lease term must be at least one day"),
+            Error::TermTooLong => write!(f, "lease term must be at most {} days", MAX_TERM),
+            Error::LeaseConflict(lease) => write!(f, "overlaps existing {}", lease),
+            Error::IllegalTransition { from, to } => {
+                write!(f, "a lease cannot go from {} to {}", from, to)
+            }
+            Error::OutsideTerm(on) => write!(f, "{} does not fit the lease term", on),
+            Error::DuplicateMeter(name) => write!(f, "meter {:?} already installed", name),
+            Error::UnknownMeter(name) => write!(f, "no meter named {:?}", name),
+            Error::InvalidTariff(reason) => write!(f, "invalid tariff: {}", reason),
+            Error::InvalidAmount(amount) => write!(f, "{} is not a valid amount", amount),
+            Error::Overflow => write!(f, "amounts too large to add up"),
+            Error::NotTenant(id) => write!(f, "tenant {} is not the tenant of that lease", id),
+            Error::NothingToInvoice(id) => {
+                write!(f, "every period of lease {} has been invoiced", id)
+            }
+            Error::NotStarted(id) => write!(f, "lease {} has not been activated", id),
+            Error::UnknownInvoice(number) => write!(f, "no invoice #{}", number),
+            Error::ReadingOutOfOrder(last) => write!(
+                f,
+                "reading must be after {} and at least {}",
+                last.on, last.value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for SiteKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SiteKind::Flat => "Flat",
+                SiteKind::Cabin => "Cabin",
+                SiteKind::House => "House",
+                SiteKind::Other(kind) => kind,
+            }
+        )
+    }
+}
+
+impl From<&str> for SiteKind {
+    fn from(s: &str) -> Self {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "cabin" => SiteKind::Cabin,
+            "house" => SiteKind::House,
+            "flat" => SiteKind::Flat,
+            _ => SiteKind::Other(s),
+        }
+    }
+}
+
+impl From<String> for SiteKind {
+    fn from(s: String) -> Self {
+        SiteKind::from(s.as_str())
+    }
+}
+
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.number)
+    }
+}
+
+impl fmt::Display for Lease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lease {} from {} until {}",
+            self.id,
+            self.term.start,
+            self.end()
+        )
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Draft => write!(f, "Draft"),
+            Status::Active => write!(f, "Active"),
+            Status::Expired => write!(f, "Expired"),
+            Status::Terminated { on, reason } => write!(f, "Terminated {} ({})", on, reason),
+            Status::Renewed { successor } => write!(f, "Renewed (next term starts {})", successor),
+        }
+    }
+}
+
+impl fmt::Display for TenantId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl fmt::Display for SiteId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl fmt::Display for LeaseId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl fmt::Display for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{day, lease, site, tenant};
+
+    // leased is state with sites 7 and 8 and a tenant, Jo, leasing site 7
+    // for four weeks from the start of January.
+    fn leased() -> (State, LeaseId) {
+        let mut state = State::default();
+        let jo = state.register_tenant(tenant("Jo")).unwrap();
+        let seven = state.list_site(site("7")).unwrap();
+        state.list_site(site("8")).unwrap();
+        let at = state.lease_site(lease(jo, seven, day(1, 1), 28)).unwrap();
+        (state, at)
+    }
+
+    #[test]
+    fn rejects_duplicate_tenants_and_sites() {
+        let (mut state, _) = leased();
+        assert_eq!(
+            state.register_tenant(tenant("Jo")),
+            Err(Error::DuplicateTenant("Jo".into()))
+        );
+        assert_eq!(
+            state.list_site(site("7")),
+            Err(Error::DuplicateSite("7".into()))
+        );
+        assert_eq!(state.tenants.len(), 1);
+        assert_eq!(state.sites.len(), 2);
+    }
+
+    #[test]
+    fn leases_only_known_tenants_and_sites() {
+        let (mut state, at) = leased();
+        let jo = state.lease(at).unwrap().tenant;
+        let eight = state.site_numbered("8").unwrap().id;
+        assert_eq!(
+            state.lease_site(lease(TenantId(9), eight, day(1, 1), 28)),
+            Err(Error::UnknownTenant(TenantId(9)))
+        );
+        assert_eq!(
+            state.lease_site(lease(jo, SiteId(9), day(1, 1), 28)),
+            Err(Error::UnknownSite(SiteId(9)))
+        );
+        assert_eq!(state.leases().count(), 1);
+    }
+
+    #[test]
+    fn rejects_overlapping_leases() {
+        let (mut state, at) = leased();
+        let existing = state.lease(at).unwrap().clone();
+        let (jo, seven) = (existing.tenant, existing.site);
+        let eight = state.site_numbered("8").unwrap().id;
+
+        assert_eq!(
+            state.lease_site(lease(jo, seven, day(1, 28), 7)),
+            Err(Error::LeaseConflict(Box::new(existing)))
+        );
+        assert_eq!(
+            state.lease_site(lease(jo, seven, day(1, 1), 0)),
+            Err(Error::EmptyTerm)
+        );
+        assert!(state.lease_site(lease(jo, seven, day(1, 29), 7)).is_ok());
+        assert!(state.lease_site(lease(jo, eight, day(1, 1), 28)).is_ok());
+    }
+
+    #[test]
+    fn rejects_terms_too_long() {
+        let (mut state, at) = leased();
+        let (jo, eight) = (
+            state.lease(at).unwrap().tenant,
+            state.site_numbered("8").unwrap().id,
+        );
+        let endless = lease(jo, eight, day(3, 1), 4_000_000_000);
+
+        assert_eq!(state.conflicting_lease(&endless), None);
+        assert_eq!(state.lease_site(endless), Err(Error::TermTooLong));
+        assert_eq!(
+            state.lease_site(lease(jo, eight, Date::MAX, 1)),
+            Err(Error::TermTooLong)
+        );
+        state.activate_lease(at, day(1, 1)).unwrap();
+        assert_eq!(
+            state.renew_lease(at, MAX_TERM + 1, None, day(1, 20)),
+            Err(Error::TermTooLong)
+        );
+        assert!(state
+            .lease_site(lease(jo, eight, day(3, 1), MAX_TERM))
+            .is_ok());
+    }
+
+    #[test]
+    fn draft_leases_leave_sites_vacant() {
+        let (mut state, at) = leased();
+        let vacant = |state: &State, on| {
+            let mut numbers: Vec<_> = state.vacant_sites(on).map(|s| s.number.clone()).collect();
+            numbers.sort();
+            numbers
+        };
+        assert_eq!(vacant(&state, day(1, 1)), ["7", "8"]);
+
+        state.activate_lease(at, day(1, 1)).unwrap();
+        assert_eq!(vacant(&state, day(1, 1)), ["8"]);
+        assert_eq!(vacant(&state, day(1, 28)), ["8"]);
+        assert_eq!(vacant(&state, day(1, 29)), ["7", "8"]);
+
+        state
+            .terminate_lease(at, day(1, 14), "left".into(), day(1, 10))
+            .unwrap();
+        assert_eq!(vacant(&state, day(1, 15)), ["7", "8"]);
+    }
+
+    #[test]
+    fn draft_leases_take_sites_for_their_term() {
+        let (state, at) = leased();
+        let free = |start, duration| {
+            let term = Term {
+                start,
+                duration,
+                ..state.lease(at).unwrap().term.clone()
+            };
+            let mut numbers: Vec<_> = state.free_sites(&term).map(|s| s.number.clone()).collect();
+            numbers.sort();
+            numbers
+        };
+        assert_eq!(free(day(1, 20), 14), ["8"]);
+        assert_eq!(free(day(1, 29), 14), ["7", "8"]);
+        assert_eq!(free(day(12, 1), 1), ["7", "8"]);
+        assert_eq!(free(day(1, 1), 1), ["8"]);
+    }
+
+    #[test]
+    fn rejects_illegal_transitions() {
+        let (mut state, at) = leased();
+        assert_eq!(
+            state.expire_lease(at, day(2, 1)),
+            Err(Error::IllegalTransition {
+                from: Status::Draft,
+                to: Status::Expired
+            })
+        );
+        state.activate_lease(at, day(1, 1)).unwrap();
+        assert_eq!(
+            state.activate_lease(at, day(1, 2)),
+            Err(Error::IllegalTransition {
+                from: Status::Active,
+                to: Status::Active
+            })
+        );
+        assert_eq!(
+            state.expire_lease(at, day(1, 28)),
+            Err(Error::OutsideTerm(day(1, 28)))
+        );
+        assert_eq!(
+            state.terminate_lease(at, day(2, 1), "left".into(), day(1, 10)),
+            Err(Error::OutsideTerm(day(2, 1)))
+        );
+        state.expire_lease(at, day(1, 29)).unwrap();
+        assert!(matches!(
+            state.terminate_lease(at, day(1, 14), "left".into(), day(1, 30)),
+            Err(Error::IllegalTransition {
+                from: Status::Expired,
+                ..
+            })
+        ));
+        let history = &state.lease(at).unwrap().history;
+        let statuses: Vec<_> = history.iter().map(|t| &t.to).collect();
+        assert_eq!(statuses, [&Status::Active, &Status::Expired]);
+    }
+
+    #[test]
+    fn links_renewals() {
+        let (mut state, at) = leased();
+        state.activate_lease(at, day(1, 1)).unwrap();
+
+        let successor = state.renew_lease(at, 14, None, day(1, 20)).unwrap();
+        let renewed = state.lease(at).unwrap();
+        let next = state.lease(successor).unwrap();
+        assert_eq!(
+            renewed.status,
+            Status::Renewed {
+                successor: day(1, 29)
+            }
+        );
+        assert_eq!(next.renews, Some(at));
+        assert_eq!(next.status, Status::Active);
+        assert_eq!(next.term.start, day(1, 29));
+        assert_eq!(next.term.rent, renewed.term.rent);
+    }
+
+    #[test]
+    fn rejects_renewals_onto_a_later_lease() {
+        let (mut state, at) = leased();
+        state.activate_lease(at, day(1, 1)).unwrap();
+        let (jo, seven) = state.lease(at).map(|l| (l.tenant, l.site)).unwrap();
+        let later = state.lease_site(lease(jo, seven, day(2, 5), 7)).unwrap();
+
+        assert_eq!(
+            state.renew_lease(at, 14, None, day(1, 20)),
+            Err(Error::LeaseConflict(Box::new(
+                state.lease(later).unwrap().clone()
+            )))
+        );
+        assert_eq!(state.lease(at).unwrap().status, Status::Active);
+        assert_eq!(state.leases().count(), 2);
+    }
+}
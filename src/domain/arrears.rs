@@ -0,0 +1,217 @@
+//! Arrears: money owed on a lease, whatever state the lease is in.
+//!
+//! A lease that has expired or been terminated can still have debt owing on
+//! it, and that debt stays with the lease and its tenant after the site is let
+//! to someone else.
+
+use super::ledger::Account;
+use super::{LeaseId, Money, State, Status, TenantId, Towards};
+use chrono::NaiveDate as Date;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Arrears {
+    pub lease: LeaseId,
+    pub tenant: TenantId,
+    /// The tenant's name and site's number when the arrears were worked out.
+    pub tenant_name: String,
+    pub site_number: String,
+    pub status: Status,
+    pub rent: Money,
+    pub utilities: Money,
+    /// since is the day the oldest charge still unpaid was posted.
+    pub since: Date,
+}
+
+impl State {
+    /// lease_arrears is what is owed on a lease by the given day, if
+    /// anything.
+    pub fn lease_arrears(&self, at: LeaseId, on: Date) -> Option<Arrears> {
+        let lease = self.lease(at)?;
+        let rent = self.owing(at, Towards::Rent, on);
+        let utilities = self.owing(at, Towards::Utilities, on);
+        if rent + utilities <= Money::ZERO {
+            return None;
+        }
+        Some(Arrears {
+            lease: at,
+            tenant: lease.tenant,
+            tenant_name: self.tenant_name(lease.tenant).to_string(),
+            site_number: self.site_number(lease.site).to_string(),
+            status: lease.status.clone(),
+            rent,
+            utilities,
+            since: self.oldest_unpaid(at, on)?,
+        })
+    }
+
+    /// arrears lists every lease with money owing by the given day, oldest
+    /// debt first.
+    pub fn arrears(&self, on: Date) -> Vec<Arrears> {
+        let mut arrears: Vec<Arrears> = self
+            .leases()
+            .filter_map(|l| self.lease_arrears(l.id, on))
+            .collect();
+        arrears.sort_by_key(|a| a.since);
+        arrears
+    }
+
+    /// tenant_arrears lists the leases a tenant owes money on.
+    pub fn tenant_arrears(&self, tenant: TenantId, on: Date) -> Vec<Arrears> {
+        self.arrears(on)
+            .into_iter()
+            .filter(|a| a.tenant == tenant)
+            .collect()
+    }
+
+    // oldest_unpaid is the date of the earliest charge on a lease not covered
+    // by payments and credits, taking those as settling the oldest charges
+    // first.
+    fn oldest_unpaid(&self, at: LeaseId, on: Date) -> Option<Date> {
+        let accounts = [
+            Account::Receivable(at, Towards::Rent),
+            Account::Receivable(at, Towards::Utilities),
+        ];
+        let postings: Vec<_> = self
+            .ledger
+            .postings()
+            .iter()
+            .filter(|p| p.on <= on)
+            .collect();
+        let mut settled: Money = postings
+            .iter()
+            .filter(|p| accounts.contains(&p.credit))
+            .map(|p| p.amount)
+            .sum();
+        let mut charges: Vec<_> = postings
+            .into_iter()
+            .filter(|p| accounts.contains(&p.debit))
+            .collect();
+        charges.sort_by_key(|p| p.on);
+        for charge in charges {
+            if settled < charge.amount {
+                return Some(charge.on);
+            }
+            settled = settled - charge.amount;
+        }
+        None
+    }
+}
+
+impl Arrears {
+    pub fn total(&self) -> Money {
+        self.rent + self.utilities
+    }
+
+    /// days is how long the oldest unpaid charge has been owing.
+    pub fn days(&self, on: Date) -> i64 {
+        (on - self.since).num_days()
+    }
+
+    /// is_current is whether the debt is on a lease still running, rather
+    /// than one that has ended.
+    pub fn is_current(&self) -> bool {
+        matches!(self.status, Status::Draft | Status::Active)
+    }
+}
+
+impl fmt::Display for Arrears {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} owing on site {} since {}",
+            self.total(),
+            &self.site_number,
+            self.since
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::payment::{Method, Payment};
+    use crate::domain::Lease;
+    use crate::testing::{day, lease, site, tenant};
+
+    // active puts a lease in force from its start.
+    fn active(state: &mut State, lease: Lease) -> LeaseId {
+        let start = lease.term.start;
+        let at = state.lease_site(lease).unwrap();
+        state.activate_lease(at, start).unwrap();
+        at
+    }
+
+    #[test]
+    fn keeps_arrears_after_a_lease_ends() {
+        let mut state = State::default();
+        let jo = state.register_tenant(tenant("Jo")).unwrap();
+        let sam = state.register_tenant(tenant("Sam")).unwrap();
+        let seven = state.list_site(site("7")).unwrap();
+        let eight = state.list_site(site("8")).unwrap();
+        let expired = active(&mut state, lease(jo, seven, day(1, 1), 28));
+        let terminated = active(&mut state, lease(jo, eight, day(1, 1), 28));
+        state.post_rent_due(day(1, 8)).unwrap();
+        state
+            .record_payment(Payment {
+                tenant: jo,
+                lease: expired,
+                amount: Money::from_units(300),
+                on: day(1, 9),
+                method: Method::Cash,
+                reference: String::new(),
+                towards: Towards::Rent,
+            })
+            .unwrap();
+        state
+            .terminate_lease(terminated, day(1, 10), "left".into(), day(1, 9))
+            .unwrap();
+        state.post_rent_due(day(1, 28)).unwrap();
+        state.expire_lease(expired, day(1, 29)).unwrap();
+        // Site 7 is let again; its new lease has arrears of its own.
+        active(&mut state, lease(sam, seven, day(1, 29), 28));
+        state.post_rent_due(day(1, 29)).unwrap();
+
+        let arrears = state.lease_arrears(expired, day(1, 29)).unwrap();
+        assert_eq!(arrears.status, Status::Expired);
+        assert_eq!(arrears.total(), Money::from_units(700));
+        assert_eq!(arrears.since, day(1, 8));
+        assert!(!arrears.is_current());
+
+        let arrears = state.lease_arrears(terminated, day(1, 29)).unwrap();
+        assert_eq!(
+            arrears.status,
+            Status::Terminated {
+                on: day(1, 10),
+                reason: "left".into()
+            }
+        );
+        assert_eq!(arrears.total(), Money::from_cents(35714));
+        assert_eq!(arrears.since, day(1, 1));
+
+        assert_eq!(state.arrears(day(1, 29)).len(), 3);
+    }
+
+    #[test]
+    fn sums_arrears_per_tenant_across_leases() {
+        let mut state = State::default();
+        let jo = state.register_tenant(tenant("Jo")).unwrap();
+        let sam = state.register_tenant(tenant("Sam")).unwrap();
+        let seven = state.list_site(site("7")).unwrap();
+        let eight = state.list_site(site("8")).unwrap();
+        active(&mut state, lease(jo, seven, day(1, 1), 28));
+        active(&mut state, lease(jo, eight, day(1, 1), 7));
+        active(&mut state, lease(sam, eight, day(1, 8), 21));
+        state.post_rent_due(day(1, 15)).unwrap();
+
+        let owed = state.tenant_arrears(jo, day(1, 15));
+        assert_eq!(owed.len(), 2);
+        assert!(owed.iter().all(|a| a.tenant == jo));
+        assert_eq!(
+            owed.iter().map(Arrears::total).sum::<Money>(),
+            Money::from_units(1000)
+        );
+        assert_eq!(state.tenant_owing(jo, day(1, 15)), Money::from_units(1000));
+        assert_eq!(state.tenant_arrears(sam, day(1, 15)).len(), 1);
+    }
+}
@@ -0,0 +1,511 @@
+//! The audit trail: when each change was made, by whom, and what it did to
+//! the tenants, sites and leases it touched.
+
+use super::tariff::TariffKey;
+use super::{
+    Business, Invoice, Lease, LeaseId, Notification, Payment, Posting, Site, SiteId, State, Tariff,
+    Tenant, TenantId,
+};
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Audited is a change as it was logged.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Audited {
+    /// When the change was made. Changes logged before the audit trail began
+    /// have no time, actor or changes.
+    #[serde(default)]
+    pub at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub actor: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub changes: Vec<Change>,
+}
+
+/// Change is one record as it was before and after a change; a record that
+/// was added has nothing before, and one that was removed nothing after.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Change {
+    pub before: Option<Record>,
+    pub after: Option<Record>,
+}
+
+/// Record is a part of state that a change can touch. Sites are recorded
+/// without their leases, which are recorded on their own.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Record {
+    Tenant(Tenant),
+    Site(Site),
+    Lease(Lease),
+    Payment(Payment),
+    Invoice(Invoice),
+    Posting(Posting),
+    Tariff(Tariff),
+    Business(Business),
+    Notification(Notification),
+}
+
+/// Subject is what an audit trail can be viewed for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Subject {
+    Tenant(TenantId),
+    Site(SiteId),
+    Lease(LeaseId),
+    /// Business is everything not kept against a tenant, site or lease.
+    Business,
+}
+
+/// Scope is which records of each kind an event can change or take out;
+/// records it adds are found without being named.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Scope {
+    pub tenants: Part<TenantId>,
+    pub sites: Part<SiteId>,
+    pub leases: Part<LeaseId>,
+    /// Payments, postings and notifications are kept by their place in
+    /// their list.
+    pub payments: Part<usize>,
+    pub invoices: Part<u32>,
+    pub postings: Part<usize>,
+    pub tariffs: Part<TariffKey>,
+    pub business: Part<()>,
+    pub notifications: Part<usize>,
+}
+
+/// Part is the records of one kind in a scope: those with the keys listed,
+/// or all of them.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Part<K> {
+    Only(Vec<K>),
+    All,
+}
+
+/// Kept is state as it was before a change, as far as the change's scope
+/// reaches.
+pub struct Kept {
+    tenants: Records<TenantId, Tenant>,
+    sites: Records<SiteId, Site>,
+    leases: Records<LeaseId, Lease>,
+    payments: Records<usize, Payment>,
+    invoices: Records<u32, Invoice>,
+    postings: Records<usize, Posting>,
+    tariffs: Records<TariffKey, Tariff>,
+    business: Records<(), Business>,
+    notifications: Records<usize, Notification>,
+}
+
+// Records are copies of the records of one kind in scope, with the keys of
+// every record so that those added later can be told apart.
+struct Records<K, T> {
+    kept: BTreeMap<K, T>,
+    keys: BTreeSet<K>,
+    all: bool,
+}
+
+impl Scope {
+    /// of is the scope of the records changes were made to. Records kept by
+    /// their place in a list are taken out by value, wherever they are, so
+    /// each of those kinds is in scope as a whole.
+    pub fn of(changes: &[Change]) -> Scope {
+        let mut scope = Scope::default();
+        for record in changes.iter().flat_map(|c| c.before.iter().chain(&c.after)) {
+            match record {
+                Record::Tenant(tenant) => scope.tenants.add(tenant.id),
+                Record::Site(site) => scope.sites.add(site.id),
+                Record::Lease(lease) => scope.leases.add(lease.id),
+                Record::Payment(_) => scope.payments = Part::All,
+                Record::Invoice(invoice) => scope.invoices.add(invoice.number),
+                Record::Posting(_) => scope.postings = Part::All,
+                Record::Tariff(tariff) => scope.tariffs.add(tariff.key()),
+                Record::Business(_) => scope.business = Part::All,
+                Record::Notification(_) => scope.notifications = Part::All,
+            }
+        }
+        scope
+    }
+}
+
+impl<K: PartialEq> Part<K> {
+    /// add brings the record with a key into scope.
+    pub fn add(&mut self, key: K) {
+        if let Part::Only(keys) = self {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    fn covers(&self, key: &K) -> bool {
+        match self {
+            Part::Only(keys) => keys.contains(key),
+            Part::All => true,
+        }
+    }
+}
+
+impl<K> Default for Part<K> {
+    fn default() -> Self {
+        Part::Only(vec![])
+    }
+}
+
+impl State {
+    /// keep copies the records in scope, for `changes_since` to compare
+    /// against once a change has been made.
+    pub fn keep(&self, scope: &Scope) -> Kept {
+        Kept {
+            tenants: Records::new(
+                self.tenants.iter().map(|(id, t)| (*id, t)),
+                &scope.tenants,
+                Clone::clone,
+            ),
+            sites: Records::new(
+                self.sites.iter().map(|(id, s)| (*id, s)),
+                &scope.sites,
+                bare,
+            ),
+            leases: Records::new(
+                self.leases().map(|l| (l.id, l)),
+                &scope.leases,
+                Clone::clone,
+            ),
+            payments: Records::new(
+                self.payments.iter().enumerate(),
+                &scope.payments,
+                Clone::clone,
+            ),
+            invoices: Records::new(
+                self.invoices.iter().map(|i| (i.number, i)),
+                &scope.invoices,
+                Clone::clone,
+            ),
+            postings: Records::new(
+                self.ledger.postings().iter().enumerate(),
+                &scope.postings,
+                Clone::clone,
+            ),
+            tariffs: Records::new(
+                self.tariffs.iter().map(|t| (t.key(), t)),
+                &scope.tariffs,
+                Clone::clone,
+            ),
+            business: Records::new(
+                std::iter::once(((), &self.business)),
+                &scope.business,
+                Clone::clone,
+            ),
+            notifications: Records::new(
+                self.notifications.iter().enumerate(),
+                &scope.notifications,
+                Clone::clone,
+            ),
+        }
+    }
+
+    /// changes_since lists the records in scope, or added since, that differ
+    /// from those kept.
+    pub fn changes_since(&self, kept: &Kept) -> Vec<Change> {
+        let mut changes = vec![];
+        kept.tenants.diff(
+            self.tenants.iter().map(|(id, t)| (*id, t)),
+            |a, b| a == b,
+            |t| Record::Tenant(t.clone()),
+            &mut changes,
+        );
+        kept.sites.diff(
+            self.sites.iter().map(|(id, s)| (*id, s)),
+            |a, b| a.number == b.number && a.kind == b.kind && a.meters == b.meters,
+            |s| Record::Site(bare(s)),
+            &mut changes,
+        );
+        kept.leases.diff(
+            self.leases().map(|l| (l.id, l)),
+            |a, b| a == b,
+            |l| Record::Lease(l.clone()),
+            &mut changes,
+        );
+        kept.payments.diff(
+            self.payments.iter().enumerate(),
+            |a, b| a == b,
+            |p| Record::Payment(p.clone()),
+            &mut changes,
+        );
+        kept.invoices.diff(
+            self.invoices.iter().map(|i| (i.number, i)),
+            |a, b| a == b,
+            |i| Record::Invoice(i.clone()),
+            &mut changes,
+        );
+        kept.postings.diff(
+            self.ledger.postings().iter().enumerate(),
+            |a, b| a == b,
+            |p| Record::Posting(p.clone()),
+            &mut changes,
+        );
+        kept.tariffs.diff(
+            self.tariffs.iter().map(|t| (t.key(), t)),
+            |a, b| a == b,
+            |t| Record::Tariff(t.clone()),
+            &mut changes,
+        );
+        kept.business.diff(
+            std::iter::once(((), &self.business)),
+            |a, b| a == b,
+            |b| Record::Business(b.clone()),
+            &mut changes,
+        );
+        kept.notifications.diff(
+            self.notifications.iter().enumerate(),
+            |a, b| a == b,
+            |n| Record::Notification(n.clone()),
+            &mut changes,
+        );
+        changes
+    }
+
+    /// restore makes the changes listed, taking out each record as it was
+    /// before and putting it back as it is after. Restoring the changes from
+    /// a state to an earlier one puts the earlier one back.
+    pub fn restore(&mut self, changes: Vec<Change>) {
+        for Change { before, after } in changes {
+            let place = match before {
+                Some(before) => self.take(before),
+                None => Place::Elsewhere,
+            };
+            if let Some(after) = after {
+                self.put(after, place);
+            }
+        }
+    }
+
+    fn take(&mut self, record: Record) -> Place {
+        match record {
+            Record::Tenant(tenant) => {
+                self.tenants.remove(&tenant.id);
+                Place::Elsewhere
+            }
+            Record::Site(site) => self
+                .sites
+                .remove(&site.id)
+                .map_or(Place::Elsewhere, |site| Place::Site(site.leases)),
+            Record::Lease(lease) => {
+                for site in self.sites.values_mut() {
+                    site.leases.retain(|l| l.id != lease.id);
+                }
+                Place::Elsewhere
+            }
+            Record::Payment(payment) => take(&mut self.payments, &payment),
+            Record::Invoice(invoice) => take(&mut self.invoices, &invoice),
+            Record::Posting(posting) => take(&mut self.ledger.postings, &posting),
+            Record::Tariff(tariff) => take(&mut self.tariffs, &tariff),
+            Record::Business(_) => Place::Elsewhere,
+            Record::Notification(notification) => take(&mut self.notifications, &notification),
+        }
+    }
+
+    fn put(&mut self, record: Record, place: Place) {
+        match record {
+            Record::Tenant(tenant) => {
+                self.tenants.insert(tenant.id, tenant);
+            }
+            Record::Site(site) => {
+                let leases = match place {
+                    Place::Site(leases) => leases,
+                    _ => vec![],
+                };
+                self.sites.insert(site.id, Site { leases, ..site });
+            }
+            Record::Lease(lease) => {
+                if let Some(site) = self.sites.get_mut(&lease.site) {
+                    site.insert_lease(lease);
+                }
+            }
+            Record::Payment(payment) => put(&mut self.payments, payment, place),
+            Record::Invoice(invoice) => put(&mut self.invoices, invoice, place),
+            Record::Posting(posting) => put(&mut self.ledger.postings, posting, place),
+            Record::Tariff(tariff) => put(&mut self.tariffs, tariff, place),
+            Record::Business(business) => self.business = business,
+            Record::Notification(notification) => put(&mut self.notifications, notification, place),
+        }
+    }
+}
+
+// Place is where a record was taken from, for the record put back in its
+// place: a position in a list, or a site's leases.
+enum Place {
+    At(usize),
+    Site(Vec<Lease>),
+    Elsewhere,
+}
+
+// take takes the last of the records equal to `record` out of a list.
+fn take<T: PartialEq>(records: &mut Vec<T>, record: &T) -> Place {
+    match records.iter().rposition(|r| r == record) {
+        Some(at) => {
+            records.remove(at);
+            Place::At(at)
+        }
+        None => Place::Elsewhere,
+    }
+}
+
+// put puts a record into a list where one was taken from, or at the end.
+fn put<T>(records: &mut Vec<T>, record: T, place: Place) {
+    match place {
+        Place::At(at) => records.insert(at, record),
+        _ => records.push(record),
+    }
+}
+
+// bare is a site without its leases, which are recorded on their own.
+fn bare(site: &Site) -> Site {
+    Site {
+        id: site.id,
+        number: site.number.clone(),
+        kind: site.kind.clone(),
+        leases: vec![],
+        meters: site.meters.clone(),
+    }
+}
+
+/// reverse lists the changes that undo `changes`: each from after back to
+/// before, the last first.
+pub fn reverse(changes: Vec<Change>) -> Vec<Change> {
+    changes
+        .into_iter()
+        .rev()
+        .map(|Change { before, after }| Change {
+            before: after,
+            after: before,
+        })
+        .collect()
+}
+
+impl<K: Ord + Clone, T> Records<K, T> {
+    fn new<'a>(
+        records: impl Iterator<Item = (K, &'a T)>,
+        scope: &Part<K>,
+        copy: impl Fn(&T) -> T,
+    ) -> Self
+    where
+        T: 'a,
+    {
+        let mut kept = BTreeMap::new();
+        let mut keys = BTreeSet::new();
+        for (key, record) in records {
+            if scope.covers(&key) {
+                kept.insert(key.clone(), copy(record));
+            }
+            keys.insert(key);
+        }
+        Records {
+            kept,
+            keys,
+            all: *scope == Part::All,
+        }
+    }
+
+    // diff adds a change for each record kept, or added since, that is not
+    // the same in `after`.
+    fn diff<'a>(
+        &'a self,
+        after: impl Iterator<Item = (K, &'a T)>,
+        same: impl Fn(&T, &T) -> bool,
+        record: impl Fn(&T) -> Record,
+        changes: &mut Vec<Change>,
+    ) {
+        let after = after
+            .filter(|(key, _)| self.all || self.kept.contains_key(key) || !self.keys.contains(key));
+        diff(
+            self.kept.iter().map(|(key, t)| (key.clone(), t)),
+            after,
+            same,
+            record,
+            changes,
+        );
+    }
+}
+
+// diff adds a change for each key whose record was added, removed, or is not
+// the same afterwards.
+fn diff<'a, K: Ord, T: 'a>(
+    before: impl Iterator<Item = (K, &'a T)>,
+    after: impl Iterator<Item = (K, &'a T)>,
+    same: impl Fn(&T, &T) -> bool,
+    record: impl Fn(&T) -> Record,
+    changes: &mut Vec<Change>,
+) {
+    let mut pairs: BTreeMap<K, (Option<&T>, Option<&T>)> = BTreeMap::new();
+    for (key, value) in before {
+        pairs.entry(key).or_default().0 = Some(value);
+    }
+    for (key, value) in after {
+        pairs.entry(key).or_default().1 = Some(value);
+    }
+    for (before, after) in pairs.into_values() {
+        if let (Some(a), Some(b)) = (before, after) {
+            if same(a, b) {
+                continue;
+            }
+        }
+        changes.push(Change {
+            before: before.map(&record),
+            after: after.map(&record),
+        });
+    }
+}
+
+impl Audited {
+    /// concerns reports whether the change touched a subject. Changes to a
+    /// lease, its payments and invoices concern its tenant and site too.
+    pub fn concerns(&self, subject: Subject, state: &State) -> bool {
+        self.changes.iter().any(|change| {
+            change
+                .before
+                .iter()
+                .chain(&change.after)
+                .any(|record| record.concerns(subject, state))
+        })
+    }
+}
+
+impl Record {
+    /// subject is what the record is kept against.
+    pub fn subject(&self) -> Subject {
+        match self {
+            Record::Tenant(tenant) => Subject::Tenant(tenant.id),
+            Record::Site(site) => Subject::Site(site.id),
+            Record::Lease(lease) => Subject::Lease(lease.id),
+            Record::Payment(payment) => Subject::Lease(payment.lease),
+            Record::Invoice(invoice) => Subject::Lease(invoice.lease),
+            Record::Posting(posting) => posting
+                .debit
+                .lease()
+                .or_else(|| posting.credit.lease())
+                .map_or(Subject::Business, Subject::Lease),
+            Record::Tariff(tariff) => tariff.site.map_or(Subject::Business, Subject::Site),
+            Record::Business(_) => Subject::Business,
+            Record::Notification(notification) => Subject::Lease(notification.reminder.lease()),
+        }
+    }
+
+    fn concerns(&self, subject: Subject, state: &State) -> bool {
+        let own = self.subject();
+        if own == subject {
+            return true;
+        }
+        let lease = match (own, self) {
+            (_, Record::Lease(lease)) => Some(lease),
+            (Subject::Lease(id), _) => state.lease(id),
+            _ => None,
+        };
+        match (subject, lease) {
+            (Subject::Tenant(tenant), Some(lease)) => lease.tenant == tenant,
+            (Subject::Site(site), Some(lease)) => lease.site == site,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Utility bills and the per-lease account they are charged to.
+
+use super::ledger::{Account, Posting, Source};
+use super::tariff::Line;
+use super::{Error, LeaseId, Money, State, Towards};
+use chrono::{Duration, NaiveDate as Date};
+use serde_derive::{Deserialize, Serialize};
+
+/// UtilityAccount is what a lease has been billed for utilities. Payments
+/// and credit are kept in the ledger.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct UtilityAccount {
+    pub bills: Vec<UtilityBill>,
+}
+
+/// UtilityBill charges a lease for utilities over the days `from` through
+/// `to`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct UtilityBill {
+    /// Bills are numbered from 1 for each lease.
+    pub number: u32,
+    pub issued: Date,
+    pub from: Date,
+    pub to: Date,
+    pub lines: Vec<Line>,
+    /// Credit from earlier overpayment put towards this bill.
+    pub credit_applied: Money,
+}
+
+impl State {
+    /// issue_utility_bill bills a lease for utilities from the day after its
+    /// last bill, or the start of the lease, through `to`. Any credit on the
+    /// account is applied to the new bill.
+    pub fn issue_utility_bill(
+        &mut self,
+        at: LeaseId,
+        to: Date,
+        on: Date,
+    ) -> Result<UtilityBill, Error> {
+        let lease = self.lease(at).ok_or(Error::UnknownLease(at))?;
+        let from = match lease.utility.bills.last() {
+            Some(last) => last.to + Duration::days(1),
+            None => lease.term.start,
+        };
+        if to < from {
+            return Err(Error::OutsideTerm(to));
+        }
+        let lines = self.utility_charges(at, from, to)?;
+        let credit = std::cmp::max(-self.owing(at, Towards::Utilities, on), Money::ZERO);
+
+        let account = &mut self.lease_mut(at)?.utility;
+        let mut bill = UtilityBill {
+            number: account.bills.len() as u32 + 1,
+            issued: on,
+            from,
+            to,
+            lines,
+            credit_applied: Money::ZERO,
+        };
+        bill.credit_applied = std::cmp::min(credit, bill.total());
+        account.bills.push(bill.clone());
+
+        if let Some(posting) = bill.posting(at) {
+            self.ledger.post(posting)?;
+        }
+        Ok(bill)
+    }
+}
+
+impl UtilityBill {
+    /// total is the sum of the bill's lines, before credit.
+    pub fn total(&self) -> Money {
+        self.lines.iter().map(|l| l.amount).sum()
+    }
+
+    /// due is what remains to be paid after credit.
+    pub fn due(&self) -> Money {
+        self.total() - self.credit_applied
+    }
+
+    /// posting is the ledger entry charging the bill to a lease, if there is
+    /// anything to charge.
+    pub fn posting(&self, at: LeaseId) -> Option<Posting> {
+        if self.total() <= Money::ZERO {
+            return None;
+        }
+        Some(Posting {
+            on: self.issued,
+            debit: Account::Receivable(at, Towards::Utilities),
+            credit: Account::UtilityIncome,
+            amount: self.total(),
+            source: Source::UtilityBill {
+                number: self.number,
+                credit_applied: self.credit_applied,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::payment::{Method, Payment};
+    use crate::domain::tariff::Tariff;
+    use crate::domain::utility::{Meter, Reading, Utility};
+    use crate::testing::{day, fixture};
+
+    // billed is the fixture lease using 100 units of electricity at $0.50 in
+    // its first two weeks and 40 in the next, and billed $50 for the first
+    // two weeks.
+    fn billed() -> (State, LeaseId) {
+        let (mut state, at) = fixture("");
+        let site = state.lease(at).unwrap().site;
+        let meter = Meter {
+            name: "House".into(),
+            utility: Utility::Electricity,
+            readings: vec![],
+        };
+        state.add_meter(site, meter).unwrap();
+        for (on, value) in [(day(1, 1), 0), (day(1, 14), 100), (day(1, 28), 140)] {
+            state
+                .record_reading(site, "House", Reading { on, value })
+                .unwrap();
+        }
+        state
+            .add_tariff(Tariff {
+                utility: Utility::Electricity,
+                site: None,
+                supply: Money::ZERO,
+                rate: "0.50".parse().unwrap(),
+            })
+            .unwrap();
+
+        let bill = state
+            .issue_utility_bill(at, day(1, 14), day(1, 15))
+            .unwrap();
+        assert_eq!(bill.total(), Money::from_units(50));
+        assert_eq!(bill.due(), Money::from_units(50));
+        (state, at)
+    }
+
+    fn pay(state: &mut State, at: LeaseId, amount: Money, on: Date) {
+        let tenant = state.lease(at).unwrap().tenant;
+        state
+            .record_payment(Payment {
+                tenant,
+                lease: at,
+                amount,
+                on,
+                method: Method::Cash,
+                reference: String::new(),
+                towards: Towards::Utilities,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn holds_overpayment_as_credit() {
+        let (mut state, at) = billed();
+        pay(&mut state, at, Money::from_units(60), day(1, 16));
+        assert_eq!(
+            state.owing(at, Towards::Utilities, day(1, 16)),
+            Money::from_units(-10)
+        );
+    }
+
+    #[test]
+    fn applies_credit_to_the_next_bill() {
+        let (mut state, at) = billed();
+        pay(&mut state, at, Money::from_units(60), day(1, 16));
+
+        let bill = state
+            .issue_utility_bill(at, day(1, 28), day(1, 29))
+            .unwrap();
+        assert_eq!(bill.from, day(1, 15));
+        assert_eq!(bill.total(), Money::from_units(20));
+        assert_eq!(bill.credit_applied, Money::from_units(10));
+        assert_eq!(bill.due(), Money::from_units(10));
+        assert_eq!(
+            state.owing(at, Towards::Utilities, day(1, 29)),
+            Money::from_units(10)
+        );
+    }
+
+    #[test]
+    fn carries_credit_beyond_the_next_bill_forward() {
+        let (mut state, at) = billed();
+        pay(&mut state, at, Money::from_units(80), day(1, 16));
+
+        let bill = state
+            .issue_utility_bill(at, day(1, 28), day(1, 29))
+            .unwrap();
+        assert_eq!(bill.credit_applied, Money::from_units(20));
+        assert_eq!(bill.due(), Money::ZERO);
+        assert_eq!(
+            state.owing(at, Towards::Utilities, day(1, 29)),
+            Money::from_units(-10)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! How to reach a tenant.
+
+use super::Channel;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// CALLING_CODE is the country calling code assumed for phone numbers
+/// written without one.
+pub const CALLING_CODE: &str = "61";
+
+/// Contact is a tenant's contact details.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Contact {
+    pub emails: Vec<Email>,
+    pub phones: Vec<Phone>,
+    /// Postal address, one line per line.
+    pub address: String,
+    /// How the tenant would rather be contacted.
+    pub preferred: Channel,
+    pub emergency: Option<EmergencyContact>,
+    /// Anything from free-form contact details that was not recognised as an
+    /// email address or phone number.
+    pub notes: String,
+}
+
+/// EmergencyContact is who to call if something happens to the tenant.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct EmergencyContact {
+    pub name: String,
+    pub relationship: String,
+    pub phone: Phone,
+}
+
+/// Email is an email address.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Email(String);
+
+/// Phone is a phone number in E.164 form, such as `+61400123456`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Phone(String);
+
+/// ParseError is returned for text that is not an email address or phone
+/// number.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError(pub String);
+
+impl Contact {
+    /// from_text recognises the email addresses and phone numbers in
+    /// free-form contact details, such as "jo@example.com, 0400 123 456",
+    /// keeping the rest as notes. The preferred channel is email if there is
+    /// an address, otherwise SMS if there is a phone number.
+    pub fn from_text(text: &str) -> Self {
+        let mut contact = Contact::default();
+        let mut notes = vec![];
+        for part in text
+            .split([',', ';', '/', '\n'])
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            if let Ok(email) = part.parse::<Email>() {
+                contact.emails.push(email);
+            } else if let Ok(phone) = part.parse::<Phone>() {
+                contact.phones.push(phone);
+            } else {
+                notes.push(part);
+            }
+        }
+        contact.notes = notes.join("\n");
+        if contact.emails.is_empty() && !contact.phones.is_empty() {
+            contact.preferred = Channel::Sms;
+        }
+        contact
+    }
+
+    pub fn email(&self) -> Option<&Email> {
+        self.emails.first()
+    }
+
+    pub fn phone(&self) -> Option<&Phone> {
+        self.phones.first()
+    }
+}
+
+impl Email {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Phone {
+    /// as_str is the number in E.164 form.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Email {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let valid = match s.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.contains('.')
+                    && !domain.starts_with('.')
+                    && !domain.ends_with('.')
+                    && !s.chars().any(|c| c.is_whitespace() || c == '<' || c == '>')
+            }
+            None => false,
+        };
+        if valid {
+            Ok(Email(s.to_string()))
+        } else {
+            Err(ParseError(format!("{:?} is not an email address", s)))
+        }
+    }
+}
+
+impl FromStr for Phone {
+    type Err = ParseError;
+
+    /// from_str normalises a phone number to E.164. Spacing and punctuation
+    /// are ignored; numbers starting "+" or "00" carry their country code,
+    /// and numbers starting "0" are taken to be in the country of
+    /// `CALLING_CODE`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError(format!("{:?} is not a phone number", s.trim()));
+        let compact: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+        let digits = if let Some(rest) = compact.strip_prefix('+') {
+            rest.to_string()
+        } else if let Some(rest) = compact.strip_prefix("00") {
+            rest.to_string()
+        } else if let Some(rest) = compact.strip_prefix('0') {
+            format!("{}{}", CALLING_CODE, rest)
+        } else {
+            return Err(invalid());
+        };
+        if !digits.chars().all(|c| c.is_ascii_digit())
+            || !(8..=15).contains(&digits.len())
+            || digits.starts_with('0')
+        {
+            return Err(invalid());
+        }
+        Ok(Phone(format!("+{}", digits)))
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl fmt::Display for Phone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl fmt::Display for EmergencyContact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.relationship.is_empty() {
+            write!(f, "{}, {}", &self.name, &self.phone)
+        } else {
+            write!(
+                f,
+                "{} ({}), {}",
+                &self.name, &self.relationship, &self.phone
+            )
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phone(s: &str) -> Result<String, ParseError> {
+        s.parse::<Phone>().map(|p| p.to_string())
+    }
+
+    #[test]
+    fn normalises_phone_numbers() {
+        for (text, e164) in [
+            ("0400 123 456", "+61400123456"),
+            ("(03) 9000-0000", "+61390000000"),
+            ("+44 20 7946 0958", "+442079460958"),
+            ("0044 20 7946 0958", "+442079460958"),
+            ("+1.212.555.0100", "+12125550100"),
+        ] {
+            assert_eq!(phone(text), Ok(e164.to_string()), "{:?}", text);
+        }
+        for text in [
+            "",
+            "400 123 456",
+            "+0400123456",
+            "+1234567",
+            "+1234567890123456",
+            "0400 ABC 456",
+            "jo@example.com",
+        ] {
+            assert!(phone(text).is_err(), "{:?}", text);
+        }
+        assert_eq!(phone("+1234567 8"), Ok("+12345678".to_string()));
+        assert_eq!(
+            phone("+123456789012345"),
+            Ok("+123456789012345".to_string())
+        );
+    }
+
+    #[test]
+    fn checks_email_addresses() {
+        assert_eq!(
+            " jo@example.com ".parse::<Email>().map(|e| e.to_string()),
+            Ok("jo@example.com".to_string())
+        );
+        for text in [
+            "jo",
+            "@example.com",
+            "jo@example",
+            "jo@.example.com",
+            "jo@example.com.",
+            "jo@@example.com",
+            "jo @example.com",
+            "Jo <jo@example.com>",
+        ] {
+            assert!(text.parse::<Email>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn reads_contact_details_from_text() {
+        let contact = Contact::from_text("jo@example.com, 0400 123 456; gate code 1234");
+        assert_eq!(contact.email().map(Email::as_str), Some("jo@example.com"));
+        assert_eq!(contact.phone().map(Phone::as_str), Some("+61400123456"));
+        assert_eq!(contact.notes, "gate code 1234");
+        assert_eq!(contact.preferred, Channel::Email);
+
+        let contact = Contact::from_text("0400 123 456 / 03 9000 0000\nafter 5pm");
+        assert_eq!(contact.phones.len(), 2);
+        assert!(contact.emails.is_empty());
+        assert_eq!(contact.notes, "after 5pm");
+        assert_eq!(contact.preferred, Channel::Sms);
+
+        assert_eq!(Contact::from_text(" , "), Contact::default());
+    }
+}
@@ -0,0 +1,398 @@
+//! Events: the changes made to state, recorded so that state can be rebuilt
+//! by replaying them.
+//!
+//! An event carries what its command was given rather than what the command
+//! worked out, so replaying events in order makes the same ids, invoices and
+//! postings as were made the first time.
+
+use super::audit::{Part, Scope};
+use super::{
+    Business, Change, Days, Delivery, Error, Lease, LeaseId, Meter, Money, Notification, Payment,
+    Reading, Site, SiteId, State, Tariff, Tenant, TenantId,
+};
+use chrono::NaiveDate as Date;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Event {
+    TenantRegistered(Tenant),
+    /// TenantRenamed gives a tenant a new name; what refers to the tenant
+    /// does so by id, and is unaffected.
+    TenantRenamed {
+        tenant: TenantId,
+        name: String,
+    },
+    SiteListed(Site),
+    /// SiteRenumbered gives a site a new number; what refers to the site
+    /// does so by id, and is unaffected.
+    SiteRenumbered {
+        site: SiteId,
+        number: String,
+    },
+    LeaseCreated(Lease),
+    LeaseActivated {
+        lease: LeaseId,
+        on: Date,
+    },
+    LeaseExpired {
+        lease: LeaseId,
+        on: Date,
+    },
+    /// LeaseTerminated ends a lease on `end`, the last day of occupancy.
+    LeaseTerminated {
+        lease: LeaseId,
+        end: Date,
+        reason: String,
+        on: Date,
+    },
+    LeaseRenewed {
+        lease: LeaseId,
+        duration: Days,
+        rent: Option<Money>,
+        on: Date,
+    },
+    MeterInstalled {
+        site: SiteId,
+        meter: Meter,
+    },
+    ReadingRecorded {
+        site: SiteId,
+        meter: String,
+        reading: Reading,
+    },
+    TariffSet(Tariff),
+    UtilityBillIssued {
+        lease: LeaseId,
+        to: Date,
+        on: Date,
+    },
+    PaymentRecorded(Payment),
+    InvoiceIssued {
+        lease: LeaseId,
+        on: Date,
+    },
+    /// InvoicesIssued invoices every lease with rent to invoice.
+    InvoicesIssued {
+        on: Date,
+    },
+    BusinessSet(Business),
+    /// RentFellDue posts the rent that had fallen due by `on`.
+    RentFellDue {
+        on: Date,
+    },
+    /// Reverted puts back the records the change described by `undid`
+    /// changed, as they were before it.
+    Reverted {
+        undid: String,
+        changes: Vec<Change>,
+    },
+    /// Redone makes the records the change described by `redid` changed as
+    /// they were after it, once it has been reverted.
+    Redone {
+        redid: String,
+        changes: Vec<Change>,
+    },
+    /// Imported adds deliveries of invoices, by number, and notifications
+    /// made against an export of state.
+    Imported {
+        deliveries: Vec<(u32, Delivery)>,
+        notifications: Vec<Notification>,
+    },
+}
+
+impl Event {
+    /// describe says in a line what the event did, naming tenants and sites
+    /// as they are in `state`.
+    pub fn describe(&self, state: &State) -> String {
+        let lease = |id: LeaseId| match state.lease(id) {
+            Some(lease) => format!(
+                "lease {} of site {} to {}",
+                id,
+                state.site_number(lease.site),
+                state.tenant_name(lease.tenant)
+            ),
+            None => format!("lease {}", id),
+        };
+        match self {
+            Event::TenantRegistered(tenant) => format!("Registered tenant {}", tenant.name),
+            Event::TenantRenamed { tenant, name } => {
+                format!("Renamed tenant {} to {}", tenant, name)
+            }
+            Event::SiteListed(site) => format!("Listed site {}", site.number),
+            Event::SiteRenumbered { site, number } => {
+                format!("Renumbered site {} as {}", site, number)
+            }
+            Event::LeaseCreated(created) => format!(
+                "Leased site {} to {} from {}",
+                state.site_number(created.site),
+                state.tenant_name(created.tenant),
+                created.term.start
+            ),
+            Event::LeaseActivated { lease: id, .. } => format!("Activated {}", lease(*id)),
+            Event::LeaseExpired { lease: id, .. } => format!("Expired {}", lease(*id)),
+            Event::LeaseTerminated { lease: id, end, .. } => {
+                format!("Terminated {} on {}", lease(*id), end)
+            }
+            Event::LeaseRenewed { lease: id, .. } => format!("Renewed {}", lease(*id)),
+            Event::MeterInstalled { site, meter } => format!(
+                "Installed {} meter {:?} on site {}",
+                meter.utility,
+                meter.name,
+                state.site_number(*site)
+            ),
+            Event::ReadingRecorded {
+                site,
+                meter,
+                reading,
+            } => format!(
+                "Read {} on meter {:?} of site {}",
+                reading.value,
+                meter,
+                state.site_number(*site)
+            ),
+            Event::TariffSet(tariff) => format!("Set tariff: {}", tariff),
+            Event::UtilityBillIssued { lease: id, to, .. } => {
+                format!("Billed utilities to {} for {}", to, lease(*id))
+            }
+            Event::PaymentRecorded(payment) => format!(
+                "Recorded payment of {} from {}",
+                payment.amount,
+                state.tenant_name(payment.tenant)
+            ),
+            Event::InvoiceIssued { lease: id, .. } => format!("Invoiced {}", lease(*id)),
+            Event::InvoicesIssued { .. } => "Invoiced all leases".to_string(),
+            Event::BusinessSet(_) => "Changed business details".to_string(),
+            Event::RentFellDue { on } => format!("Posted rent due by {}", on),
+            Event::Reverted { undid, .. } => format!("Undid: {}", undid),
+            Event::Redone { redid, .. } => format!("Redid: {}", redid),
+            Event::Imported {
+                deliveries,
+                notifications,
+            } => format!(
+                "Imported {} invoice deliveries and {} notifications",
+                deliveries.len(),
+                notifications.len()
+            ),
+        }
+    }
+
+    /// scope is the records the event can change or take out, besides those
+    /// it adds.
+    pub fn scope(&self) -> Scope {
+        let mut scope = Scope::default();
+        match self {
+            Event::TenantRegistered(_)
+            | Event::LeaseCreated(_)
+            | Event::PaymentRecorded(_)
+            | Event::RentFellDue { .. } => {}
+            // Leases listed along with a site are moved onto it.
+            Event::SiteListed(site) => {
+                for lease in &site.leases {
+                    scope.leases.add(lease.id);
+                }
+            }
+            Event::LeaseActivated { lease, .. }
+            | Event::LeaseExpired { lease, .. }
+            | Event::LeaseTerminated { lease, .. }
+            | Event::LeaseRenewed { lease, .. }
+            | Event::UtilityBillIssued { lease, .. }
+            | Event::InvoiceIssued { lease, .. } => scope.leases.add(*lease),
+            Event::MeterInstalled { site, .. } | Event::ReadingRecorded { site, .. } => {
+                scope.sites.add(*site)
+            }
+            Event::TenantRenamed { tenant, .. } => scope.tenants.add(*tenant),
+            Event::SiteRenumbered { site, .. } => scope.sites.add(*site),
+            Event::TariffSet(tariff) => scope.tariffs.add(tariff.key()),
+            Event::InvoicesIssued { .. } => scope.leases = Part::All,
+            Event::BusinessSet(_) => scope.business = Part::All,
+            Event::Reverted { changes, .. } | Event::Redone { changes, .. } => {
+                scope = Scope::of(changes)
+            }
+            Event::Imported { deliveries, .. } => {
+                for (number, _) in deliveries {
+                    scope.invoices.add(*number);
+                }
+            }
+        }
+        scope
+    }
+
+    /// imported is the event bringing into `state` the invoice deliveries and
+    /// notifications in `file` that it lacks, such as those `avisha-mail` and
+    /// `avisha-sms` record against an export. Nothing else is taken from the
+    /// file, so changes made since the export are kept.
+    pub fn imported(state: &State, file: &State) -> Event {
+        let mut deliveries = vec![];
+        for invoice in &file.invoices {
+            let ours = match state.invoices.iter().find(|i| i.number == invoice.number) {
+                Some(ours) if ours.lease == invoice.lease => ours,
+                _ => continue,
+            };
+            for delivery in &invoice.deliveries {
+                if !ours.deliveries.contains(delivery) {
+                    deliveries.push((invoice.number, delivery.clone()));
+                }
+            }
+        }
+        let notifications = file
+            .notifications
+            .iter()
+            .filter(|n| state.tenant(n.tenant).is_some() && !state.notifications.contains(n))
+            .cloned()
+            .collect();
+        Event::Imported {
+            deliveries,
+            notifications,
+        }
+    }
+}
+
+impl State {
+    /// apply makes the change an event records, by issuing the command it
+    /// came from.
+    pub fn apply(&mut self, event: Event) -> Result<(), Error> {
+        match event {
+            Event::TenantRegistered(tenant) => self.register_tenant(tenant).map(|_| ()),
+            Event::TenantRenamed { tenant, name } => self.rename_tenant(tenant, name),
+            Event::SiteListed(site) => self.list_site(site).map(|_| ()),
+            Event::SiteRenumbered { site, number } => self.renumber_site(site, number),
+            Event::LeaseCreated(lease) => self.lease_site(lease).map(|_| ()),
+            Event::LeaseActivated { lease, on } => self.activate_lease(lease, on),
+            Event::LeaseExpired { lease, on } => self.expire_lease(lease, on),
+            Event::LeaseTerminated {
+                lease,
+                end,
+                reason,
+                on,
+            } => self.terminate_lease(lease, end, reason, on),
+            Event::LeaseRenewed {
+                lease,
+                duration,
+                rent,
+                on,
+            } => self.renew_lease(lease, duration, rent, on).map(|_| ()),
+            Event::MeterInstalled { site, meter } => self.add_meter(site, meter),
+            Event::ReadingRecorded {
+                site,
+                meter,
+                reading,
+            } => self.record_reading(site, &meter, reading),
+            Event::TariffSet(tariff) => self.add_tariff(tariff),
+            Event::UtilityBillIssued { lease, to, on } => {
+                self.issue_utility_bill(lease, to, on).map(|_| ())
+            }
+            Event::PaymentRecorded(payment) => self.record_payment(payment),
+            Event::InvoiceIssued { lease, on } => self.issue_invoice(lease, on).map(|_| ()),
+            Event::InvoicesIssued { on } => self.issue_invoices(on).map(|_| ()),
+            Event::BusinessSet(business) => {
+                self.business = business;
+                Ok(())
+            }
+            Event::RentFellDue { on } => self.post_rent_due(on),
+            Event::Reverted { changes, .. } | Event::Redone { changes, .. } => {
+                self.restore(changes);
+                Ok(())
+            }
+            Event::Imported {
+                deliveries,
+                notifications,
+            } => {
+                for (number, delivery) in deliveries {
+                    self.record_delivery(number, delivery)?;
+                }
+                for notification in notifications {
+                    self.log_notification(notification)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Channel, DeliveryStatus, Reminder};
+    use crate::testing::{day, fixture, tenant};
+
+    #[test]
+    fn renames_without_losing_leases() {
+        let (mut state, at) = fixture("jo@example.com");
+        let lease = state.lease(at).unwrap().clone();
+        state
+            .apply(Event::TenantRenamed {
+                tenant: lease.tenant,
+                name: "Jo Smith".into(),
+            })
+            .unwrap();
+        state
+            .apply(Event::SiteRenumbered {
+                site: lease.site,
+                number: "7A".into(),
+            })
+            .unwrap();
+
+        assert_eq!(state.lease(at), Some(&lease));
+        assert_eq!(state.tenant_name(lease.tenant), "Jo Smith");
+        assert_eq!(state.site_number(lease.site), "7A");
+        assert!(state.tenant_named("Jo Citizen").is_none());
+
+        let sam = state.register_tenant(tenant("Sam")).unwrap();
+        assert_eq!(
+            state.apply(Event::TenantRenamed {
+                tenant: sam,
+                name: "Jo Smith".into(),
+            }),
+            Err(Error::DuplicateTenant("Jo Smith".into()))
+        );
+    }
+
+    #[test]
+    fn imports_only_deliveries_and_notifications() {
+        let (mut state, at) = fixture("jo@example.com");
+        state.issue_invoice(at, day(1, 1)).unwrap();
+        let mut file = state.clone();
+        let delivery = Delivery {
+            on: day(1, 2),
+            to: "jo@example.com".into(),
+            status: DeliveryStatus::Sent,
+        };
+        file.record_delivery(1, delivery.clone()).unwrap();
+        let notification = Notification {
+            tenant: state.lease(at).unwrap().tenant,
+            on: day(1, 6),
+            channel: Channel::Sms,
+            to: "+61400123456".into(),
+            reminder: Reminder::RentDue {
+                lease: at,
+                due: day(1, 8),
+            },
+            body: "Rent is due".into(),
+            status: DeliveryStatus::Sent,
+        };
+        file.log_notification(notification.clone()).unwrap();
+        file.register_tenant(tenant("Sam")).unwrap();
+        state.register_tenant(tenant("Al")).unwrap();
+
+        let imported = Event::imported(&state, &file);
+        assert_eq!(
+            imported,
+            Event::Imported {
+                deliveries: vec![(1, delivery)],
+                notifications: vec![notification],
+            }
+        );
+        state.apply(imported).unwrap();
+        assert!(state.invoices[0].is_delivered());
+        assert_eq!(state.notifications.len(), 1);
+        assert!(state.tenant_named("Al").is_some());
+        assert!(state.tenant_named("Sam").is_none());
+        assert_eq!(
+            Event::imported(&state, &file),
+            Event::Imported {
+                deliveries: vec![],
+                notifications: vec![],
+            }
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Invoices: what a tenant is asked to pay for a rent period.
+//!
+//! An invoice charges the rent for one period of a lease in advance, along
+//! with utilities used up to the start of that period, and carries forward
+//! whatever was owing or held in credit beforehand.
+
+use super::ledger::{Account, Posting, Source};
+use super::rent::Charge;
+use super::{Error, LeaseId, Money, State, TenantId, Towards};
+use chrono::{Duration, NaiveDate as Date};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// PAYMENT_TERMS is the least number of days a tenant is given to pay an
+/// invoice.
+pub const PAYMENT_TERMS: i64 = 7;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Invoice {
+    /// Invoices are numbered from 1 across all leases.
+    pub number: u32,
+    pub lease: LeaseId,
+    pub tenant: TenantId,
+    pub issued: Date,
+    pub due: Date,
+    /// The rent period invoiced, `from` through `to`.
+    pub from: Date,
+    pub to: Date,
+    pub lines: Vec<InvoiceLine>,
+    /// What was owing on the lease before this invoice.
+    pub previous_balance: Money,
+    /// Credit held on the lease put towards this invoice.
+    pub credit_applied: Money,
+    /// How many utility bills the lease had when this invoice was issued,
+    /// including any issued with it.
+    pub utility_bills: u32,
+    /// Attempts to send the invoice to the tenant, oldest first.
+    #[serde(default)]
+    pub deliveries: Vec<Delivery>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub amount: Money,
+}
+
+/// Delivery is one attempt to send an invoice to its tenant.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Delivery {
+    pub on: Date,
+    /// Where the invoice was sent, such as an email address.
+    pub to: String,
+    pub status: DeliveryStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum DeliveryStatus {
+    Sent,
+    /// Failed carries the reason the invoice could not be sent.
+    Failed(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvoiceStatus {
+    Unpaid,
+    Paid,
+    Overdue,
+}
+
+impl State {
+    /// issue_invoice invoices the next period of a lease that has not yet been
+    /// invoiced. Utilities are billed up to the day before the period starts,
+    /// and the period's rent is charged to the lease. Draft leases cannot be
+    /// invoiced.
+    pub fn issue_invoice(&mut self, at: LeaseId, on: Date) -> Result<Invoice, Error> {
+        let lease = self.lease(at).ok_or(Error::UnknownLease(at))?;
+        if !lease.status.is_started() {
+            return Err(Error::NotStarted(at));
+        }
+        let tenant = lease.tenant;
+        let bill_from = match lease.utility.bills.last() {
+            Some(last) => last.to + Duration::days(1),
+            None => lease.term.start,
+        };
+        let charge = self.next_charge(at).ok_or(Error::NothingToInvoice(at))?;
+
+        let receivable = Account::Receivable(at, Towards::Rent);
+        let rent_source = Source::Rent { due: charge.due };
+        let rent_posted: Money = self
+            .ledger
+            .postings()
+            .iter()
+            .filter(|p| p.debit == receivable && p.source == rent_source)
+            .map(|p| p.amount)
+            .sum();
+        let previous = self.lease_owing(at, Date::MAX) - rent_posted;
+
+        let mut lines = vec![InvoiceLine {
+            description: format!("Rent {} to {}", charge.from, charge.to),
+            amount: charge.amount,
+        }];
+        let bill_to = charge.from - Duration::days(1);
+        if bill_to >= bill_from {
+            let bill = self.issue_utility_bill(at, bill_to, on)?;
+            lines.extend(bill.lines.into_iter().map(|l| InvoiceLine {
+                description: format!("{}: {}", l.utility, l.description),
+                amount: l.amount,
+            }));
+        }
+        if rent_posted.is_zero() && !charge.amount.is_zero() {
+            self.ledger.post(Posting {
+                on: charge.due,
+                debit: receivable,
+                credit: Account::RentIncome,
+                amount: charge.amount,
+                source: rent_source,
+            })?;
+        }
+
+        let mut invoice = Invoice {
+            number: self.invoices.len() as u32 + 1,
+            lease: at,
+            tenant,
+            issued: on,
+            due: std::cmp::max(charge.due, on + Duration::days(PAYMENT_TERMS)),
+            from: charge.from,
+            to: charge.to,
+            lines,
+            previous_balance: std::cmp::max(previous, Money::ZERO),
+            credit_applied: Money::ZERO,
+            utility_bills: self.lease(at).map_or(0, |l| l.utility.bills.len() as u32),
+            deliveries: vec![],
+        };
+        invoice.credit_applied = std::cmp::min(-previous, invoice.total()).max(Money::ZERO);
+        self.invoices.push(invoice.clone());
+        Ok(invoice)
+    }
+
+    /// issue_invoices invoices every period, on every lease, whose rent falls
+    /// due within the payment terms of the given day.
+    pub fn issue_invoices(&mut self, on: Date) -> Result<Vec<Invoice>, Error> {
+        let horizon = on + Duration::days(PAYMENT_TERMS);
+        let leases: Vec<LeaseId> = self
+            .leases()
+            .filter(|l| l.status.is_started())
+            .map(|l| l.id)
+            .collect();
+        let mut issued = vec![];
+        for at in leases {
+            while self.next_charge(at).is_some_and(|c| c.due <= horizon) {
+                issued.push(self.issue_invoice(at, on)?);
+            }
+        }
+        Ok(issued)
+    }
+
+    /// record_delivery notes an attempt to send an invoice.
+    pub fn record_delivery(&mut self, number: u32, delivery: Delivery) -> Result<(), Error> {
+        self.invoices
+            .iter_mut()
+            .find(|i| i.number == number)
+            .ok_or(Error::UnknownInvoice(number))?
+            .deliveries
+            .push(delivery);
+        Ok(())
+    }
+
+    /// invoices_for lists the invoices issued on a lease.
+    pub fn invoices_for(&self, at: LeaseId) -> impl Iterator<Item = &Invoice> {
+        self.invoices.iter().filter(move |i| i.lease == at)
+    }
+
+    /// invoice_status is whether an invoice has been paid by the given day.
+    /// Payments and credits settle the oldest charges on a lease first, so an
+    /// invoice is paid once everything charged up to and including it is.
+    pub fn invoice_status(&self, invoice: &Invoice, on: Date) -> InvoiceStatus {
+        let accounts = [
+            Account::Receivable(invoice.lease, Towards::Rent),
+            Account::Receivable(invoice.lease, Towards::Utilities),
+        ];
+        let postings = self.ledger.postings();
+        let settled: Money = postings
+            .iter()
+            .filter(|p| p.on <= on && accounts.contains(&p.credit))
+            .map(|p| p.amount)
+            .sum();
+        let charged: Money = postings
+            .iter()
+            .filter(|p| accounts.contains(&p.debit))
+            .filter(|p| match p.source {
+                Source::Rent { due } => due <= invoice.to,
+                Source::UtilityBill { number, .. } => number <= invoice.utility_bills,
+                _ => p.on <= invoice.issued,
+            })
+            .map(|p| p.amount)
+            .sum();
+        if settled >= charged {
+            InvoiceStatus::Paid
+        } else if on > invoice.due {
+            InvoiceStatus::Overdue
+        } else {
+            InvoiceStatus::Unpaid
+        }
+    }
+
+    // next_charge is the first rent period of a lease after the last one
+    // invoiced.
+    fn next_charge(&self, at: LeaseId) -> Option<Charge> {
+        let invoiced = self.invoices_for(at).map(|i| i.to).max();
+        self.lease(at)?
+            .rent_schedule()
+            .into_iter()
+            .find(|c| invoiced.is_none_or(|to| c.from > to))
+    }
+}
+
+impl Invoice {
+    /// total is the sum of the invoice's lines.
+    pub fn total(&self) -> Money {
+        self.lines.iter().map(|l| l.amount).sum()
+    }
+
+    /// amount_due is what the tenant is asked to pay: the previous balance and
+    /// this invoice's lines, less credit.
+    pub fn amount_due(&self) -> Money {
+        self.previous_balance + self.total() - self.credit_applied
+    }
+
+    /// is_delivered reports whether the invoice has reached the tenant.
+    pub fn is_delivered(&self) -> bool {
+        self.deliveries
+            .iter()
+            .any(|d| d.status == DeliveryStatus::Sent)
+    }
+}
+
+impl fmt::Display for InvoiceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                InvoiceStatus::Unpaid => "Unpaid",
+                InvoiceStatus::Paid => "Paid",
+                InvoiceStatus::Overdue => "Overdue",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Delivery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            DeliveryStatus::Sent => write!(f, "{}: sent to {}", self.on, &self.to),
+            DeliveryStatus::Failed(reason) => {
+                write!(f, "{}: sending to {} failed: {}", self.on, &self.to, reason)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Invoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invoice #{} for {} to {}: {} due {}",
+            self.number,
+            self.from,
+            self.to,
+            self.amount_due(),
+            self.due
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::payment::{Method, Payment};
+    use crate::testing::{day, fixture, lease, site};
+
+    // leased is the fixture with its tenant also leasing site 8 on the same
+    // terms.
+    fn leased() -> (State, LeaseId, LeaseId) {
+        let (mut state, seven) = fixture("");
+        let tenant = state.lease(seven).unwrap().tenant;
+        let site = state.list_site(site("8")).unwrap();
+        let eight = state
+            .lease_site(lease(tenant, site, day(1, 1), 28))
+            .unwrap();
+        state.activate_lease(eight, day(1, 1)).unwrap();
+        (state, seven, eight)
+    }
+
+    #[test]
+    fn invoices_only_leases_in_force() {
+        let (mut state, at) = fixture("");
+        let (tenant, site) = state.lease(at).map(|l| (l.tenant, l.site)).unwrap();
+        let draft = state.lease_site(lease(tenant, site, day(2, 1), 7)).unwrap();
+
+        assert_eq!(
+            state.issue_invoice(draft, day(2, 1)),
+            Err(Error::NotStarted(draft))
+        );
+        let issued = state.issue_invoices(day(2, 1)).unwrap();
+        assert!(issued.iter().all(|i| i.lease == at));
+        assert!(state.invoices_for(draft).next().is_none());
+        assert_eq!(state.owing(draft, Towards::Rent, day(2, 7)), Money::ZERO);
+    }
+
+    #[test]
+    fn numbers_invoices_across_leases() {
+        let (mut state, seven, eight) = leased();
+        let first = state.issue_invoice(seven, day(1, 1)).unwrap();
+        let second = state.issue_invoice(eight, day(1, 1)).unwrap();
+        let third = state.issue_invoice(seven, day(1, 6)).unwrap();
+
+        assert_eq!([first.number, second.number, third.number], [1, 2, 3]);
+        assert_eq!((first.from, first.to), (day(1, 1), day(1, 7)));
+        assert_eq!((third.from, third.to), (day(1, 8), day(1, 14)));
+        assert_eq!(first.due, day(1, 8));
+        assert_eq!(state.invoices_for(seven).count(), 2);
+    }
+
+    #[test]
+    fn carries_what_was_owing_forward() {
+        let (mut state, at) = fixture("");
+        let first = state.issue_invoice(at, day(1, 1)).unwrap();
+        assert_eq!(first.previous_balance, Money::ZERO);
+        assert_eq!(first.amount_due(), Money::from_units(250));
+
+        let second = state.issue_invoice(at, day(1, 6)).unwrap();
+        assert_eq!(second.previous_balance, Money::from_units(250));
+        assert_eq!(second.credit_applied, Money::ZERO);
+        assert_eq!(second.amount_due(), Money::from_units(500));
+        assert_eq!(
+            state.invoice_status(&first, day(1, 9)),
+            InvoiceStatus::Overdue
+        );
+    }
+
+    #[test]
+    fn applies_credit_held_on_the_lease() {
+        let (mut state, at) = fixture("");
+        let tenant = state.lease(at).unwrap().tenant;
+        state
+            .record_payment(Payment {
+                tenant,
+                lease: at,
+                amount: Money::from_units(300),
+                on: day(1, 1),
+                method: Method::Cash,
+                reference: String::new(),
+                towards: Towards::Rent,
+            })
+            .unwrap();
+
+        let first = state.issue_invoice(at, day(1, 1)).unwrap();
+        assert_eq!(first.previous_balance, Money::ZERO);
+        assert_eq!(first.credit_applied, Money::from_units(250));
+        assert_eq!(first.amount_due(), Money::ZERO);
+        assert_eq!(state.invoice_status(&first, day(1, 1)), InvoiceStatus::Paid);
+
+        let second = state.issue_invoice(at, day(1, 6)).unwrap();
+        assert_eq!(second.credit_applied, Money::from_units(50));
+        assert_eq!(second.amount_due(), Money::from_units(200));
+        assert_eq!(
+            state.invoice_status(&second, day(1, 6)),
+            InvoiceStatus::Unpaid
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//! Double-entry ledger behind every balance.
+//!
+//! Each posting moves an amount from one account to another, so the books
+//! always balance. Postings are only ever appended; mistakes are corrected by
+//! further postings.
+
+use super::payment::Towards;
+use super::{Error, LeaseId, Money, State, TenantId};
+use chrono::NaiveDate as Date;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Ledger {
+    pub(super) postings: Vec<Posting>,
+}
+
+/// Posting debits one account and credits another by the same amount.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Posting {
+    pub on: Date,
+    pub debit: Account,
+    pub credit: Account,
+    pub amount: Money,
+    pub source: Source,
+}
+
+/// Account is somewhere money is owed, earned or held.
+///
+/// Balances are debits less credits, so what tenants owe and cash on hand are
+/// positive, while income and bond held are negative.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Account {
+    /// What the tenant of a lease owes towards rent or utilities. A negative
+    /// balance is credit held for the tenant.
+    Receivable(LeaseId, Towards),
+    /// Bond lodged by the tenant of a lease, held until it is returned.
+    BondHeld(LeaseId),
+    RentIncome,
+    UtilityIncome,
+    Cash,
+    /// Debts forgiven.
+    WrittenOff,
+}
+
+/// Source is what gave rise to a posting.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Source {
+    Rent {
+        due: Date,
+    },
+    /// Corrects rent already charged for days a lease no longer covers, such
+    /// as after an early termination.
+    RentAdjustment,
+    UtilityBill {
+        number: u32,
+        credit_applied: Money,
+    },
+    Payment {
+        reference: String,
+    },
+    BondReturned,
+    WriteOff {
+        reason: String,
+    },
+}
+
+impl Ledger {
+    pub fn postings(&self) -> &[Posting] {
+        &self.postings
+    }
+
+    /// post appends a posting; amounts must be positive. Every balance is
+    /// made up of amounts posted, so a posting that would take their total
+    /// past what `Money` can hold is refused rather than letting a balance
+    /// overflow.
+    pub fn post(&mut self, posting: Posting) -> Result<(), Error> {
+        if posting.amount <= Money::ZERO {
+            return Err(Error::InvalidAmount(posting.amount));
+        }
+        self.postings
+            .iter()
+            .try_fold(posting.amount, |total, p| total.checked_add(p.amount))
+            .ok_or(Error::Overflow)?;
+        self.postings.push(posting);
+        Ok(())
+    }
+
+    /// balance is debits less credits to an account over all time.
+    pub fn balance(&self, account: &Account) -> Money {
+        self.balance_by(account, Date::MAX)
+    }
+
+    /// balance_by is debits less credits to an account up to and including
+    /// the given day.
+    pub fn balance_by(&self, account: &Account, on: Date) -> Money {
+        self.postings
+            .iter()
+            .filter(|p| p.on <= on)
+            .map(|p| p.effect(account))
+            .sum()
+    }
+
+    /// trial_balance is the balance of every account that has been posted
+    /// to, in order of first use. The balances always sum to zero.
+    pub fn trial_balance(&self) -> Vec<(Account, Money)> {
+        let mut accounts: Vec<&Account> = vec![];
+        for p in &self.postings {
+            for account in [&p.debit, &p.credit] {
+                if !accounts.contains(&account) {
+                    accounts.push(account);
+                }
+            }
+        }
+        accounts
+            .into_iter()
+            .map(|a| (a.clone(), self.balance(a)))
+            .collect()
+    }
+
+    /// lease_postings are the postings touching any account of a lease.
+    pub fn lease_postings(&self, at: LeaseId) -> impl Iterator<Item = &Posting> {
+        self.postings
+            .iter()
+            .filter(move |p| p.debit.lease() == Some(at) || p.credit.lease() == Some(at))
+    }
+}
+
+impl Posting {
+    // effect is the change this posting makes to an account's balance.
+    fn effect(&self, account: &Account) -> Money {
+        if &self.debit == account {
+            self.amount
+        } else if &self.credit == account {
+            -self.amount
+        } else {
+            Money::ZERO
+        }
+    }
+}
+
+impl Account {
+    /// lease is the lease an account belongs to, if any.
+    pub fn lease(&self) -> Option<LeaseId> {
+        match self {
+            Account::Receivable(at, _) | Account::BondHeld(at) => Some(*at),
+            _ => None,
+        }
+    }
+}
+
+impl State {
+    /// post_rent_due charges every lease put into force for rent that has
+    /// fallen due by the given day and has not yet been charged; draft leases
+    /// owe nothing. Where a lease now covers fewer days than it was charged
+    /// for, such as after an early termination, the excess is credited back.
+    pub fn post_rent_due(&mut self, on: Date) -> Result<(), Error> {
+        let mut postings = vec![];
+        for lease in self.leases().filter(|l| l.status.is_started()) {
+            let receivable = Account::Receivable(lease.id, Towards::Rent);
+            let mut charged = Money::ZERO;
+            let mut dues = vec![];
+            for p in self
+                .ledger
+                .postings()
+                .iter()
+                .filter(|p| p.debit == receivable)
+            {
+                if let Source::Rent { due } = p.source {
+                    charged = charged.checked_add(p.amount).ok_or(Error::Overflow)?;
+                    dues.push(due);
+                }
+            }
+            for p in self
+                .ledger
+                .postings()
+                .iter()
+                .filter(|p| p.credit == receivable)
+            {
+                if p.source == Source::RentAdjustment {
+                    charged = charged.checked_sub(p.amount).ok_or(Error::Overflow)?;
+                }
+            }
+
+            let schedule = lease.rent_schedule();
+            let expected = schedule
+                .iter()
+                .filter(|c| dues.contains(&c.due))
+                .try_fold(Money::ZERO, |sum, c| sum.checked_add(c.amount))
+                .ok_or(Error::Overflow)?;
+            if charged > expected {
+                postings.push(Posting {
+                    on,
+                    debit: Account::RentIncome,
+                    credit: receivable.clone(),
+                    amount: charged.checked_sub(expected).ok_or(Error::Overflow)?,
+                    source: Source::RentAdjustment,
+                });
+            }
+            for charge in schedule
+                .iter()
+                .filter(|c| c.due <= on && !dues.contains(&c.due) && !c.amount.is_zero())
+            {
+                postings.push(Posting {
+                    on: charge.due,
+                    debit: receivable.clone(),
+                    credit: Account::RentIncome,
+                    amount: charge.amount,
+                    source: Source::Rent { due: charge.due },
+                });
+            }
+        }
+        for posting in postings {
+            self.ledger.post(posting)?;
+        }
+        Ok(())
+    }
+
+    /// write_off forgives an amount owed on a lease.
+    pub fn write_off(
+        &mut self,
+        at: LeaseId,
+        towards: Towards,
+        amount: Money,
+        reason: String,
+        on: Date,
+    ) -> Result<(), Error> {
+        if self.lease(at).is_none() {
+            return Err(Error::UnknownLease(at));
+        }
+        self.ledger.post(Posting {
+            on,
+            debit: Account::WrittenOff,
+            credit: Account::Receivable(at, towards),
+            amount,
+            source: Source::WriteOff { reason },
+        })
+    }
+
+    /// return_bond pays back bond held for a lease.
+    pub fn return_bond(&mut self, at: LeaseId, amount: Money, on: Date) -> Result<(), Error> {
+        let held = -self.ledger.balance(&Account::BondHeld(at));
+        if amount > held {
+            return Err(Error::InvalidAmount(amount));
+        }
+        self.ledger.post(Posting {
+            on,
+            debit: Account::BondHeld(at),
+            credit: Account::Cash,
+            amount,
+            source: Source::BondReturned,
+        })
+    }
+
+    /// owing is what the tenant of a lease owes towards rent or utilities
+    /// by the given day. A negative amount is credit.
+    pub fn owing(&self, at: LeaseId, towards: Towards, on: Date) -> Money {
+        self.ledger
+            .balance_by(&Account::Receivable(at, towards), on)
+    }
+
+    /// bond_held is the bond lodged for a lease and not yet returned.
+    pub fn bond_held(&self, at: LeaseId) -> Money {
+        -self.ledger.balance(&Account::BondHeld(at))
+    }
+
+    /// lease_owing is what the tenant of a lease owes in total by the given
+    /// day.
+    pub fn lease_owing(&self, at: LeaseId, on: Date) -> Money {
+        self.owing(at, Towards::Rent, on) + self.owing(at, Towards::Utilities, on)
+    }
+
+    /// tenant_owing is what a tenant owes across all of their leases by the
+    /// given day.
+    pub fn tenant_owing(&self, tenant: TenantId, on: Date) -> Money {
+        self.leases()
+            .filter(|l| l.tenant == tenant)
+            .map(|l| self.lease_owing(l.id, on))
+            .sum()
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Account::Receivable(at, towards) => write!(f, "{} receivable, lease {}", towards, at),
+            Account::BondHeld(at) => write!(f, "Bond held, lease {}", at),
+            Account::RentIncome => write!(f, "Rent income"),
+            Account::UtilityIncome => write!(f, "Utility income"),
+            Account::Cash => write!(f, "Cash"),
+            Account::WrittenOff => write!(f, "Written off"),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Rent { due } => write!(f, "Rent due {}", due),
+            Source::RentAdjustment => write!(f, "Rent adjustment"),
+            Source::UtilityBill {
+                number,
+                credit_applied,
+            } if credit_applied.is_zero() => write!(f, "Utility bill #{}", number),
+            Source::UtilityBill {
+                number,
+                credit_applied,
+            } => write!(
+                f,
+                "Utility bill #{} ({} credit applied)",
+                number, credit_applied
+            ),
+            Source::Payment { reference } if reference.is_empty() => write!(f, "Payment"),
+            Source::Payment { reference } => write!(f, "Payment (ref {})", reference),
+            Source::BondReturned => write!(f, "Bond returned"),
+            Source::WriteOff { reason } => write!(f, "Written off: {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for Posting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {}, debit {}, credit {}",
+            self.on, self.source, self.amount, self.debit, self.credit
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::payment::{Method, Payment};
+    use crate::testing::{day, fixture, lease};
+
+    fn pay(state: &mut State, at: LeaseId, amount: Money, towards: Towards, on: Date) {
+        let tenant = state.lease(at).unwrap().tenant;
+        state
+            .record_payment(Payment {
+                tenant,
+                lease: at,
+                amount,
+                on,
+                method: Method::Cash,
+                reference: String::new(),
+                towards,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn balances_the_books() {
+        let (mut state, at) = fixture("");
+        state.post_rent_due(day(1, 8)).unwrap();
+        pay(
+            &mut state,
+            at,
+            Money::from_units(300),
+            Towards::Rent,
+            day(1, 3),
+        );
+        pay(
+            &mut state,
+            at,
+            Money::from_units(500),
+            Towards::Bond,
+            day(1, 3),
+        );
+
+        let balances = state.ledger.trial_balance();
+        assert_eq!(
+            balances,
+            [
+                (
+                    Account::Receivable(at, Towards::Rent),
+                    Money::from_units(200)
+                ),
+                (Account::RentIncome, Money::from_units(-500)),
+                (Account::Cash, Money::from_units(800)),
+                (Account::BondHeld(at), Money::from_units(-500)),
+            ]
+        );
+        assert_eq!(balances.iter().map(|(_, m)| m).sum::<Money>(), Money::ZERO);
+    }
+
+    #[test]
+    fn refuses_postings_past_what_money_holds() {
+        let posting = |amount| Posting {
+            on: day(1, 1),
+            debit: Account::Cash,
+            credit: Account::RentIncome,
+            amount,
+            source: Source::Payment {
+                reference: String::new(),
+            },
+        };
+        let mut ledger = Ledger::default();
+        ledger.post(posting(Money::from_cents(i64::MAX))).unwrap();
+        assert_eq!(
+            ledger.post(posting(Money::from_cents(1))),
+            Err(Error::Overflow)
+        );
+        assert_eq!(ledger.postings().len(), 1);
+    }
+
+    #[test]
+    fn credits_rent_charged_past_termination() {
+        let (mut state, at) = fixture("");
+        state.post_rent_due(day(1, 8)).unwrap();
+        state
+            .terminate_lease(at, day(1, 10), "left".into(), day(1, 9))
+            .unwrap();
+        state.post_rent_due(day(1, 10)).unwrap();
+        state.post_rent_due(day(1, 20)).unwrap();
+
+        let adjustments: Vec<_> = state
+            .ledger
+            .postings()
+            .iter()
+            .filter(|p| p.source == Source::RentAdjustment)
+            .map(|p| (p.on, p.amount))
+            .collect();
+        assert_eq!(adjustments, [(day(1, 10), Money::from_cents(14286))]);
+        assert_eq!(
+            state.owing(at, Towards::Rent, day(1, 20)),
+            Money::from_cents(35714)
+        );
+    }
+
+    #[test]
+    fn writes_off_debts() {
+        let (mut state, at) = fixture("");
+        state.post_rent_due(day(1, 1)).unwrap();
+        state
+            .write_off(
+                at,
+                Towards::Rent,
+                Money::from_units(50),
+                "goodwill".into(),
+                day(1, 2),
+            )
+            .unwrap();
+        assert_eq!(
+            state.owing(at, Towards::Rent, day(1, 2)),
+            Money::from_units(200)
+        );
+        assert_eq!(
+            state.ledger.balance(&Account::WrittenOff),
+            Money::from_units(50)
+        );
+        assert_eq!(
+            state.write_off(at, Towards::Rent, Money::ZERO, "none".into(), day(1, 2)),
+            Err(Error::InvalidAmount(Money::ZERO))
+        );
+        assert_eq!(
+            state.write_off(
+                LeaseId(9),
+                Towards::Rent,
+                Money::from_units(1),
+                "none".into(),
+                day(1, 2)
+            ),
+            Err(Error::UnknownLease(LeaseId(9)))
+        );
+    }
+
+    #[test]
+    fn returns_no_more_bond_than_held() {
+        let (mut state, at) = fixture("");
+        pay(
+            &mut state,
+            at,
+            Money::from_units(500),
+            Towards::Bond,
+            day(1, 1),
+        );
+        assert_eq!(state.bond_held(at), Money::from_units(500));
+
+        assert_eq!(
+            state.return_bond(at, Money::from_units(600), day(1, 29)),
+            Err(Error::InvalidAmount(Money::from_units(600)))
+        );
+        state
+            .return_bond(at, Money::from_units(400), day(1, 29))
+            .unwrap();
+        state
+            .return_bond(at, Money::from_units(100), day(1, 29))
+            .unwrap();
+        assert_eq!(state.bond_held(at), Money::ZERO);
+        assert_eq!(state.ledger.balance(&Account::Cash), Money::ZERO);
+    }
+
+    #[test]
+    fn charges_no_rent_on_draft_leases() {
+        let (mut state, at) = fixture("");
+        let (tenant, site) = state.lease(at).map(|l| (l.tenant, l.site)).unwrap();
+        let draft = state.lease_site(lease(tenant, site, day(2, 1), 7)).unwrap();
+
+        state.post_rent_due(day(2, 7)).unwrap();
+        assert_eq!(state.owing(draft, Towards::Rent, day(2, 7)), Money::ZERO);
+        assert_eq!(
+            state.owing(at, Towards::Rent, day(2, 7)),
+            Money::from_units(1000)
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Fixed-point amounts of money.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_derive::Deserialize as DeriveDeserialize;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+/// Money is an amount in whole cents.
+///
+/// Arithmetic is checked: the `checked_*` methods report overflow and the
+/// operators panic on it rather than silently wrapping. Amounts read from text
+/// are limited to `Money::MAX` either way, so sums of them stay far from
+/// overflowing.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Money {
+    cents: i64,
+}
+
+/// Locale controls how amounts are written and read.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Locale {
+    pub symbol: String,
+    pub thousands: char,
+    pub decimal: char,
+}
+
+/// ParseError is returned for text that is not an amount of money.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError(pub String);
+
+impl Money {
+    pub const ZERO: Money = Money { cents: 0 };
+
+    /// MAX is the largest amount that will be read, positive or negative.
+    pub const MAX: Money = Money::from_units(1_000_000_000);
+
+    pub const fn from_cents(cents: i64) -> Self {
+        Money { cents }
+    }
+
+    pub const fn from_units(units: i64) -> Self {
+        Money { cents: units * 100 }
+    }
+
+    pub fn cents(self) -> i64 {
+        self.cents
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.cents == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.cents < 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.cents.checked_add(other.cents).map(Money::from_cents)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.cents.checked_sub(other.cents).map(Money::from_cents)
+    }
+
+    pub fn checked_mul(self, n: i64) -> Option<Money> {
+        self.cents.checked_mul(n).map(Money::from_cents)
+    }
+
+    /// pro_rata is `part / whole` of this amount, rounded half away from zero
+    /// to the nearest cent.
+    pub fn pro_rata(self, part: u64, whole: u64) -> Money {
+        let whole = i128::from(whole.max(1));
+        let scaled = i128::from(self.cents) * i128::from(part);
+        let half = if scaled < 0 { -whole / 2 } else { whole / 2 };
+        Money::from_cents(((scaled + half) / whole) as i64)
+    }
+
+    /// format writes the amount with the locale's symbol and separators,
+    /// for example "-$1,234.50".
+    pub fn format(self, locale: &Locale) -> String {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let abs = self.cents.unsigned_abs();
+        let units = (abs / 100).to_string();
+
+        let mut grouped = String::new();
+        for (ii, digit) in units.chars().enumerate() {
+            if ii > 0 && (units.len() - ii).is_multiple_of(3) {
+                grouped.push(locale.thousands);
+            }
+            grouped.push(digit);
+        }
+
+        format!(
+            "{}{}{}{}{:02}",
+            sign,
+            locale.symbol,
+            grouped,
+            locale.decimal,
+            abs % 100
+        )
+    }
+
+    /// parse reads an amount written in the given locale. The symbol and
+    /// thousands separators are optional, as are the cents. Amounts beyond
+    /// `Money::MAX` are refused.
+    pub fn parse(s: &str, locale: &Locale) -> Result<Money, ParseError> {
+        let err = || ParseError(s.to_string());
+
+        let mut text = s.trim();
+        let negative = text.starts_with('-');
+        if negative {
+            text = text[1..].trim_start();
+        }
+        text = text.strip_prefix(locale.symbol.as_str()).unwrap_or(text);
+
+        let text: String = text.chars().filter(|c| *c != locale.thousands).collect();
+        let mut parts = text.splitn(2, locale.decimal);
+        let units = parts.next().unwrap_or_default();
+        let fraction = parts.next().unwrap_or_default();
+
+        if units.is_empty() && fraction.is_empty() {
+            return Err(err());
+        }
+        if !units.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+            || fraction.len() > 2
+        {
+            return Err(err());
+        }
+
+        let units: i64 = if units.is_empty() {
+            0
+        } else {
+            units.parse().map_err(|_| err())?
+        };
+        let cents: i64 = match fraction.len() {
+            0 => 0,
+            1 => fraction.parse::<i64>().map_err(|_| err())? * 10,
+            _ => fraction.parse().map_err(|_| err())?,
+        };
+
+        let amount = units
+            .checked_mul(100)
+            .and_then(|c| c.checked_add(cents))
+            .filter(|c| *c <= Money::MAX.cents)
+            .ok_or_else(err)?;
+        Ok(Money::from_cents(if negative { -amount } else { amount }))
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            symbol: "$".into(),
+            thousands: ',',
+            decimal: '.',
+        }
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        self.checked_add(other).expect("money overflow")
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        self.checked_sub(other).expect("money overflow")
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::from_cents(self.cents.checked_neg().expect("money overflow"))
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl FromStr for Money {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Money::parse(s, &Locale::default())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&Locale::default()))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is not an amount of money", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Money is stored as a plain decimal string such as "1234.50" so that it reads
+// unambiguously regardless of locale.
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let abs = self.cents.unsigned_abs();
+        serializer.serialize_str(&format!("{}{}.{:02}", sign, abs / 100, abs % 100))
+    }
+}
+
+// Amounts saved before Money existed were whole numbers of dollars.
+#[derive(DeriveDeserialize)]
+#[serde(untagged)]
+enum Stored {
+    Units(i64),
+    Decimal(String),
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Stored::deserialize(deserializer)? {
+            Stored::Units(units) => units
+                .checked_mul(100)
+                .filter(|c| c.abs() <= Money::MAX.cents)
+                .map(Money::from_cents)
+                .ok_or_else(|| serde::de::Error::custom("money overflow")),
+            Stored::Decimal(s) => {
+                Money::parse(&s, &Locale::default().plain()).map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
+
+impl Locale {
+    // plain is the locale used for storage: no symbol or grouping.
+    fn plain(self) -> Self {
+        Locale {
+            symbol: String::new(),
+            thousands: '\u{0}',
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::Error as ValueError;
+    use serde::de::IntoDeserializer;
+
+    fn european() -> Locale {
+        Locale {
+            symbol: "€".into(),
+            thousands: '.',
+            decimal: ',',
+        }
+    }
+
+    #[test]
+    fn parses_amounts() {
+        for (text, cents) in [
+            ("$1,234.50", 123450),
+            ("1234.5", 123450),
+            ("  12 ", 1200),
+            (".05", 5),
+            ("-$3.10", -310),
+            ("- 3", -300),
+        ] {
+            assert_eq!(text.parse(), Ok(Money::from_cents(cents)), "{:?}", text);
+        }
+        for text in [
+            "",
+            "-",
+            "$",
+            "1.234",
+            "12a",
+            "$-3",
+            "1.2.3",
+            "99999999999999999999",
+            "92233720368547758",
+            "1000000000.01",
+            "-1,000,000,000.01",
+        ] {
+            assert_eq!(
+                text.parse::<Money>(),
+                Err(ParseError(text.into())),
+                "{:?}",
+                text
+            );
+        }
+        assert_eq!(
+            Money::parse("€1.234,5", &european()),
+            Ok(Money::from_cents(123450))
+        );
+        assert_eq!("1,000,000,000".parse(), Ok(Money::MAX));
+        assert_eq!("-1000000000.00".parse(), Ok(-Money::MAX));
+    }
+
+    #[test]
+    fn formats_amounts() {
+        assert_eq!(Money::ZERO.to_string(), "$0.00");
+        assert_eq!(Money::from_cents(5).to_string(), "$0.05");
+        assert_eq!(Money::from_cents(-123456789).to_string(), "-$1,234,567.89");
+        assert_eq!(Money::from_units(100).to_string(), "$100.00");
+        assert_eq!(Money::from_cents(123450).format(&european()), "€1.234,50");
+        let money = Money::from_cents(-123450);
+        assert_eq!(money.to_string().parse(), Ok(money));
+    }
+
+    #[test]
+    fn rounds_pro_rata_half_away_from_zero() {
+        let rent = Money::from_units(100);
+        assert_eq!(rent.pro_rata(1, 3), Money::from_cents(3333));
+        assert_eq!(rent.pro_rata(2, 3), Money::from_cents(6667));
+        assert_eq!(Money::from_cents(5).pro_rata(1, 2), Money::from_cents(3));
+        assert_eq!(Money::from_cents(-5).pro_rata(1, 2), Money::from_cents(-3));
+        assert_eq!(rent.pro_rata(7, 7), rent);
+        assert_eq!(rent.pro_rata(1, 0), rent);
+    }
+
+    #[test]
+    fn reports_overflow() {
+        let most = Money::from_cents(i64::MAX);
+        assert_eq!(most.checked_add(Money::from_cents(1)), None);
+        assert_eq!((-most).checked_sub(Money::from_cents(2)), None);
+        assert_eq!(most.checked_mul(2), None);
+        assert_eq!(
+            Money::from_cents(2).checked_mul(3),
+            Some(Money::from_cents(6))
+        );
+        assert!(std::panic::catch_unwind(|| most + Money::from_cents(1)).is_err());
+    }
+
+    #[test]
+    fn reads_whole_dollars_saved_before_money() {
+        let units = |n: i64| {
+            Money::deserialize(n.into_deserializer()).map_err(|e: ValueError| e.to_string())
+        };
+        let text = |s: &str| {
+            Money::deserialize(s.into_deserializer()).map_err(|e: ValueError| e.to_string())
+        };
+        assert_eq!(units(250), Ok(Money::from_units(250)));
+        assert_eq!(units(-3), Ok(Money::from_units(-3)));
+        assert_eq!(text("1234.50"), Ok(Money::from_cents(123450)));
+        assert!(units(i64::MAX).is_err());
+        assert!(units(1_000_000_001).is_err());
+        assert!(text("$1,234.50").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Notifications sent to tenants, kept as a log against each tenant.
+
+use super::{DeliveryStatus, Error, LeaseId, State, TenantId};
+use chrono::NaiveDate as Date;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use strum_macros::EnumIter;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Notification {
+    pub tenant: TenantId,
+    pub on: Date,
+    pub channel: Channel,
+    /// Where the notification was sent, such as a phone number.
+    pub to: String,
+    pub reminder: Reminder,
+    pub body: String,
+    pub status: DeliveryStatus,
+}
+
+/// Channel is a way of reaching a tenant.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, EnumIter)]
+pub enum Channel {
+    #[default]
+    Email,
+    Sms,
+}
+
+/// Reminder is what a notification reminds the tenant of.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Reminder {
+    /// RentDue is rent falling due soon on a lease.
+    RentDue { lease: LeaseId, due: Date },
+    /// Overdue is money owing on a lease since the given day.
+    Overdue { lease: LeaseId, since: Date },
+}
+
+impl State {
+    /// log_notification records a notification sent, or attempted, to a
+    /// tenant.
+    pub fn log_notification(&mut self, notification: Notification) -> Result<(), Error> {
+        if !self.tenants.contains_key(&notification.tenant) {
+            return Err(Error::UnknownTenant(notification.tenant));
+        }
+        self.notifications.push(notification);
+        Ok(())
+    }
+
+    /// notifications_for lists the notifications sent to a tenant, oldest
+    /// first.
+    pub fn notifications_for(&self, tenant: TenantId) -> impl Iterator<Item = &Notification> {
+        self.notifications
+            .iter()
+            .filter(move |n| n.tenant == tenant)
+    }
+}
+
+impl Reminder {
+    pub fn lease(&self) -> LeaseId {
+        match self {
+            Reminder::RentDue { lease, .. } | Reminder::Overdue { lease, .. } => *lease,
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Email => write!(f, "Email"),
+            Channel::Sms => write!(f, "SMS"),
+        }
+    }
+}
+
+impl fmt::Display for Reminder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reminder::RentDue { due, .. } => write!(f, "rent due {}", due),
+            Reminder::Overdue { since, .. } => write!(f, "overdue since {}", since),
+        }
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} to {}, {}",
+            self.on, self.channel, &self.to, self.reminder
+        )?;
+        if let DeliveryStatus::Failed(reason) = &self.status {
+            write!(f, " (failed: {})", reason)?;
+        }
+        Ok(())
+    }
+}
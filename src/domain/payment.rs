@@ -0,0 +1,132 @@
+//! Payments made by tenants towards rent or utilities.
+
+use super::ledger::{Account, Posting, Source};
+use super::{Error, LeaseId, Money, State, TenantId};
+use chrono::NaiveDate as Date;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use strum_macros::EnumIter;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Payment {
+    pub tenant: TenantId,
+    pub lease: LeaseId,
+    pub amount: Money,
+    pub on: Date,
+    pub method: Method,
+    /// Receipt number, bank reference or similar.
+    pub reference: String,
+    pub towards: Towards,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, EnumIter)]
+pub enum Method {
+    #[default]
+    Cash,
+    BankTransfer,
+    Card,
+    Cheque,
+}
+
+/// Towards is which account a payment is put against.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, EnumIter)]
+pub enum Towards {
+    #[default]
+    Rent,
+    Utilities,
+    /// Bond is held on the tenant's behalf rather than settling a debt.
+    Bond,
+}
+
+impl State {
+    /// record_payment accepts a payment from the tenant of a lease. Paying
+    /// more than is owing leaves credit on the account, which is consumed by
+    /// later charges.
+    pub fn record_payment(&mut self, payment: Payment) -> Result<(), Error> {
+        if !self.tenants.contains_key(&payment.tenant) {
+            return Err(Error::UnknownTenant(payment.tenant));
+        }
+        let lease = self
+            .lease(payment.lease)
+            .ok_or(Error::UnknownLease(payment.lease))?;
+        if lease.tenant != payment.tenant {
+            return Err(Error::NotTenant(payment.tenant));
+        }
+        self.ledger.post(payment.posting())?;
+        self.payments.push(payment);
+        Ok(())
+    }
+
+    /// payments_for lists the payments made against a lease.
+    pub fn payments_for(&self, at: LeaseId) -> impl Iterator<Item = &Payment> {
+        self.payments.iter().filter(move |p| p.lease == at)
+    }
+}
+
+impl Payment {
+    /// posting is the ledger entry for the payment.
+    pub fn posting(&self) -> Posting {
+        Posting {
+            on: self.on,
+            debit: Account::Cash,
+            credit: self.towards.account(self.lease),
+            amount: self.amount,
+            source: Source::Payment {
+                reference: self.reference.clone(),
+            },
+        }
+    }
+}
+
+impl Towards {
+    /// account is the ledger account a payment towards this is credited to.
+    pub fn account(self, at: LeaseId) -> Account {
+        match self {
+            Towards::Bond => Account::BondHeld(at),
+            towards => Account::Receivable(at, towards),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Method::Cash => "Cash",
+                Method::BankTransfer => "Bank Transfer",
+                Method::Card => "Card",
+                Method::Cheque => "Cheque",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Towards {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Towards::Rent => "Rent",
+                Towards::Utilities => "Utilities",
+                Towards::Bond => "Bond",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Payment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} towards {} by {}",
+            self.on, self.amount, self.towards, self.method
+        )?;
+        if !self.reference.is_empty() {
+            write!(f, " (ref {})", &self.reference)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Rent charges produced from a lease term.
+
+use super::{Days, Lease, Money, Term};
+use chrono::{Duration, Months, NaiveDate as Date};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use strum_macros::EnumIter;
+
+/// Frequency is how often rent falls due.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Hash, Eq, Debug, Default, EnumIter)]
+pub enum Frequency {
+    Nightly,
+    Weekly,
+    /// Leases saved before frequencies existed were charged fortnightly.
+    #[default]
+    Fortnightly,
+    FourWeekly,
+    /// Monthly periods run to the same day of the following calendar month.
+    Monthly,
+}
+
+/// Charge is rent owed in advance for the days `from` through `to`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Charge {
+    pub due: Date,
+    pub from: Date,
+    pub to: Date,
+    pub amount: Money,
+}
+
+impl Frequency {
+    /// period_start is the first day of the `n`th period of a term starting
+    /// on `start`. Monthly periods are anchored to `start` so that a term
+    /// starting on the 31st does not drift after a short month.
+    pub fn period_start(self, start: Date, n: u32) -> Date {
+        match self {
+            Frequency::Monthly => start
+                .checked_add_months(Months::new(n))
+                .unwrap_or(Date::MAX),
+            _ => start + Duration::days(i64::from(self.days().unwrap_or(1) * n)),
+        }
+    }
+
+    /// days is the fixed length of a period, or `None` for calendar months.
+    pub fn days(self) -> Option<Days> {
+        match self {
+            Frequency::Nightly => Some(1),
+            Frequency::Weekly => Some(7),
+            Frequency::Fortnightly => Some(14),
+            Frequency::FourWeekly => Some(28),
+            Frequency::Monthly => None,
+        }
+    }
+
+    /// per is the noun used when quoting rent, as in "$300 per fortnight".
+    pub fn per(self) -> &'static str {
+        match self {
+            Frequency::Nightly => "night",
+            Frequency::Weekly => "week",
+            Frequency::Fortnightly => "fortnight",
+            Frequency::FourWeekly => "four weeks",
+            Frequency::Monthly => "month",
+        }
+    }
+}
+
+impl Term {
+    /// schedule is every rent charge over the term.
+    pub fn schedule(&self) -> Vec<Charge> {
+        schedule(self.start, self.end(), self.rent, self.frequency)
+    }
+}
+
+impl Lease {
+    /// rent_schedule is every rent charge up to the last day of occupancy, so
+    /// a terminated lease stops accruing rent when it ends.
+    pub fn rent_schedule(&self) -> Vec<Charge> {
+        schedule(
+            self.term.start,
+            self.end(),
+            self.term.rent,
+            self.term.frequency,
+        )
+    }
+}
+
+// schedule splits the inclusive range `start..=end` into whole periods, the
+// last of which is charged pro rata for the days it covers.
+fn schedule(start: Date, end: Date, rent: Money, frequency: Frequency) -> Vec<Charge> {
+    let mut charges = vec![];
+    let mut n = 0;
+    let mut from = start;
+    while from <= end {
+        let next = frequency.period_start(start, n + 1);
+        let to = std::cmp::min(next - Duration::days(1), end);
+        let full = (next - from).num_days() as u64;
+        let days = (to - from).num_days() as u64 + 1;
+        charges.push(Charge {
+            due: from,
+            from,
+            to,
+            amount: rent.pro_rata(days, full),
+        });
+        n += 1;
+        from = next;
+    }
+    charges
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Frequency::Nightly => "Nightly",
+                Frequency::Weekly => "Weekly",
+                Frequency::Fortnightly => "Fortnightly",
+                Frequency::FourWeekly => "Four Weekly",
+                Frequency::Monthly => "Monthly",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::day;
+
+    fn term(start: Date, duration: Days, frequency: Frequency) -> Term {
+        Term {
+            start,
+            duration,
+            rent: Money::from_units(310),
+            frequency,
+        }
+    }
+
+    #[test]
+    fn charges_the_last_period_pro_rata() {
+        let charges = term(day(1, 1), 10, Frequency::Weekly).schedule();
+        assert_eq!(
+            charges,
+            [
+                Charge {
+                    due: day(1, 1),
+                    from: day(1, 1),
+                    to: day(1, 7),
+                    amount: Money::from_units(310),
+                },
+                Charge {
+                    due: day(1, 8),
+                    from: day(1, 8),
+                    to: day(1, 10),
+                    amount: Money::from_cents(13286),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn anchors_months_to_the_start() {
+        let starts: Vec<_> = (0..4)
+            .map(|n| Frequency::Monthly.period_start(day(1, 31), n))
+            .collect();
+        assert_eq!(starts, [day(1, 31), day(2, 29), day(3, 31), day(4, 30)]);
+
+        let charges = term(day(1, 31), 91, Frequency::Monthly).schedule();
+        let periods: Vec<_> = charges.iter().map(|c| (c.from, c.to, c.amount)).collect();
+        assert_eq!(
+            periods,
+            [
+                (day(1, 31), day(2, 28), Money::from_units(310)),
+                (day(2, 29), day(3, 30), Money::from_units(310)),
+                (day(3, 31), day(4, 29), Money::from_units(310)),
+                (day(4, 30), day(4, 30), Money::from_units(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_rent_when_a_lease_ends_early() {
+        let (mut state, at) = crate::testing::fixture("");
+        state
+            .terminate_lease(at, day(1, 10), "left".into(), day(1, 10))
+            .unwrap();
+        let charges = state.lease(at).unwrap().rent_schedule();
+        assert_eq!(charges.last().unwrap().to, day(1, 10));
+        assert_eq!(charges.last().unwrap().amount, Money::from_cents(10714));
+    }
+}
@@ -0,0 +1,553 @@
+//! Pricing of utility consumption.
+
+use super::utility::{Usage, Utility};
+use super::{Error, LeaseId, Money, SiteId, State};
+use chrono::NaiveDate as Date;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Tariff prices one utility, either for every site on the property or for
+/// a single site. A site's own tariff takes precedence.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Tariff {
+    pub utility: Utility,
+    /// The site this tariff is limited to, or `None` for the whole property.
+    pub site: Option<SiteId>,
+    /// Charged for each day a lease is supplied.
+    pub supply: Money,
+    pub rate: Rate,
+}
+
+/// Rate is the price of consumption.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum Rate {
+    /// The same price for every unit.
+    Flat(UnitPrice),
+    /// Successive blocks of daily consumption, each with its own price. The
+    /// last block should have no limit.
+    Tiered(Vec<Block>),
+    /// Consumption split across bands by share. Meters here are read by hand
+    /// and record a single register, so each band's share is an assumed
+    /// profile rather than measured.
+    TimeOfUse(Vec<Band>),
+}
+
+/// Block prices consumption up to `limit` units per day.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Block {
+    pub limit: Option<u64>,
+    pub price: UnitPrice,
+}
+
+/// Band prices `share` percent of consumption.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Band {
+    pub name: String,
+    pub share: u8,
+    pub price: UnitPrice,
+}
+
+/// UnitPrice is the price of one unit in ten-thousandths of a dollar, since
+/// utilities are priced in fractions of a cent.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default,
+)]
+pub struct UnitPrice(pub i64);
+
+/// Line is one itemised utility charge.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Line {
+    pub utility: Utility,
+    pub description: String,
+    pub amount: Money,
+}
+
+impl State {
+    /// add_tariff sets the tariff for a utility on its scope, replacing any
+    /// tariff it supersedes.
+    pub fn add_tariff(&mut self, tariff: Tariff) -> Result<(), Error> {
+        if let Some(site) = tariff.site {
+            if !self.sites.contains_key(&site) {
+                return Err(Error::UnknownSite(site));
+            }
+        }
+        tariff.check()?;
+        self.tariffs
+            .retain(|t| !(t.utility == tariff.utility && t.site == tariff.site));
+        self.tariffs.push(tariff);
+        Ok(())
+    }
+
+    /// tariff finds the tariff that applies to a utility on a site.
+    pub fn tariff(&self, site: SiteId, utility: Utility) -> Option<&Tariff> {
+        let mut tariffs = self.tariffs.iter().filter(|t| t.utility == utility);
+        tariffs
+            .clone()
+            .find(|t| t.site == Some(site))
+            .or_else(|| tariffs.find(|t| t.site.is_none()))
+    }
+
+    /// utility_charges itemises what a lease owes for utilities consumed over
+    /// the days `from` through `to`: for each metered utility, a supply charge
+    /// for each day the lease was supplied and the priced consumption on each
+    /// of its meters.
+    ///
+    /// Usage between readings that straddles the period is included pro
+    /// rata by days. Consumption too great to price is an error.
+    pub fn utility_charges(&self, at: LeaseId, from: Date, to: Date) -> Result<Vec<Line>, Error> {
+        let (site, lease) = match self.lease(at).and_then(|l| Some((self.site(l.site)?, l))) {
+            Some(found) => found,
+            None => return Ok(vec![]),
+        };
+
+        let supplied_from = std::cmp::max(from, lease.term.start);
+        let supplied_to = std::cmp::min(to, lease.end());
+        let supplied_days = if supplied_from <= supplied_to {
+            (supplied_to - supplied_from).num_days() as u64 + 1
+        } else {
+            0
+        };
+
+        let usage = self.lease_usage(at);
+        let mut lines = vec![];
+        let mut supplied = vec![];
+        for meter in &site.meters {
+            let tariff = match self.tariff(site.id, meter.utility) {
+                Some(tariff) => tariff,
+                None => continue,
+            };
+
+            // Supply is charged once however many meters a utility has.
+            let first = !supplied.contains(&meter.utility);
+            supplied.push(meter.utility);
+            if first && supplied_days > 0 && !tariff.supply.is_zero() {
+                lines.push(Line {
+                    utility: meter.utility,
+                    description: format!("Supply, {} days at {}", supplied_days, tariff.supply),
+                    amount: tariff
+                        .supply
+                        .checked_mul(supplied_days as i64)
+                        .ok_or(Error::Overflow)?,
+                });
+            }
+
+            let units = usage
+                .iter()
+                .filter(|u| u.meter == meter.name)
+                .try_fold(0u64, |sum, u| sum.checked_add(within(u, from, to)))
+                .ok_or(Error::Overflow)?;
+            if units > 0 {
+                lines.extend(tariff.rate.price(units, supplied_days)?.into_iter().map(
+                    |(description, amount)| Line {
+                        utility: meter.utility,
+                        description: format!("{} {}", &meter.name, description),
+                        amount,
+                    },
+                ));
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// TariffKey is what tells tariffs apart: a utility and the site the tariff
+/// is limited to. Setting a tariff replaces any with the same key.
+pub type TariffKey = (u8, Option<SiteId>);
+
+impl Tariff {
+    pub fn key(&self) -> TariffKey {
+        (self.utility as u8, self.site)
+    }
+
+    /// check rejects rates that would leave consumption unpriced.
+    pub fn check(&self) -> Result<(), Error> {
+        match &self.rate {
+            Rate::Tiered(blocks) if blocks.last().and_then(|b| b.limit).is_some() => Err(
+                Error::InvalidTariff("the last block must have no limit".into()),
+            ),
+            Rate::TimeOfUse(bands)
+                if bands.iter().map(|b| u32::from(b.share)).sum::<u32>() != 100 =>
+            {
+                Err(Error::InvalidTariff("band shares must total 100%".into()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// within is the part of the usage falling in `from..=to`, pro rata by days.
+fn within(usage: &Usage, from: Date, to: Date) -> u64 {
+    let start = std::cmp::max(from, usage.from);
+    let end = std::cmp::min(to, usage.to);
+    if start > end {
+        return 0;
+    }
+    let days = (end - start).num_days() as u64 + 1;
+    let total = (usage.to - usage.from).num_days() as u64 + 1;
+    // Widened so the product cannot overflow; the share is at most
+    // `usage.units`, so it fits back.
+    (u128::from(usage.units) * u128::from(days) / u128::from(total)) as u64
+}
+
+impl Rate {
+    /// price splits `units` consumed over `days` into described amounts.
+    pub fn price(&self, units: u64, days: u64) -> Result<Vec<(String, Money)>, Error> {
+        match self {
+            Rate::Flat(price) => Ok(vec![(
+                format!("usage, {} units at {}", units, price),
+                price.times(units)?,
+            )]),
+            Rate::Tiered(blocks) => {
+                let mut lines = vec![];
+                let mut remaining = units;
+                let mut floor = 0;
+                for (ii, block) in blocks.iter().enumerate() {
+                    if remaining == 0 {
+                        break;
+                    }
+                    // Daily limits scale with the days in the period; a limit
+                    // too large to scale takes everything that is left.
+                    let size = match block.limit {
+                        Some(limit) => limit.saturating_mul(days.max(1)).saturating_sub(floor),
+                        None => remaining,
+                    };
+                    let take = std::cmp::min(size, remaining);
+                    lines.push((
+                        format!("block {}, {} units at {}", ii + 1, take, block.price),
+                        block.price.times(take)?,
+                    ));
+                    remaining -= take;
+                    floor += take;
+                }
+                Ok(lines)
+            }
+            Rate::TimeOfUse(bands) => {
+                let mut lines = vec![];
+                let mut allocated = 0;
+                for (ii, band) in bands.iter().enumerate() {
+                    // The last band takes the remainder so no unit is lost
+                    // to rounding.
+                    let take = if ii == bands.len() - 1 {
+                        units - allocated
+                    } else {
+                        (u128::from(units) * u128::from(band.share) / 100) as u64
+                    };
+                    allocated += take;
+                    lines.push((
+                        format!("{}, {} units at {}", &band.name, take, band.price),
+                        band.price.times(take)?,
+                    ));
+                }
+                Ok(lines)
+            }
+        }
+    }
+}
+
+impl UnitPrice {
+    /// times is the cost of `units` rounded to the nearest cent, or an error
+    /// if that is more than `Money` can hold.
+    pub fn times(self, units: u64) -> Result<Money, Error> {
+        let hundredths = units
+            .checked_mul(self.0.max(0) as u64)
+            .ok_or(Error::Overflow)?;
+        Ok(Money::from_cents(1).pro_rata(hundredths, 100))
+    }
+}
+
+impl FromStr for UnitPrice {
+    type Err = Error;
+
+    /// Reads a dollar amount with up to four decimal places, such as "0.2834".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || Error::InvalidTariff(format!("{:?} is not a unit price", s));
+        let s = s.trim().trim_start_matches('$');
+        let mut parts = s.splitn(2, '.');
+        let units = parts.next().unwrap_or_default();
+        let fraction = parts.next().unwrap_or_default();
+        if (units.is_empty() && fraction.is_empty())
+            || fraction.len() > 4
+            || !units
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(err());
+        }
+        let units: i64 = if units.is_empty() {
+            0
+        } else {
+            units.parse().map_err(|_| err())?
+        };
+        let fraction: i64 = format!("{:0<4}", fraction).parse().map_err(|_| err())?;
+        units
+            .checked_mul(10_000)
+            .and_then(|u| u.checked_add(fraction))
+            .map(UnitPrice)
+            .ok_or_else(err)
+    }
+}
+
+impl FromStr for Rate {
+    type Err = Error;
+
+    /// Reads a rate written as one of:
+    ///
+    /// - a flat price: `0.28`
+    /// - daily blocks, the last without a limit: `10@0.25, 0.30`
+    /// - time of use bands: `peak 40%@0.35, off-peak 60%@0.20`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        if parts.iter().any(|p| p.contains('%')) {
+            let bands = parts
+                .iter()
+                .map(|p| {
+                    let (band, price) = split_at(p, '@', s)?;
+                    let (name, share) = match band.rfind(' ') {
+                        Some(ii) => (band[..ii].trim(), band[ii..].trim()),
+                        None => ("", band),
+                    };
+                    let share = share
+                        .trim_end_matches('%')
+                        .parse()
+                        .map_err(|_| invalid_rate(s))?;
+                    Ok(Band {
+                        name: name.to_string(),
+                        share,
+                        price: price.parse()?,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            return Ok(Rate::TimeOfUse(bands));
+        }
+        if parts.len() == 1 && !s.contains('@') {
+            return Ok(Rate::Flat(s.parse()?));
+        }
+        let blocks = parts
+            .iter()
+            .map(|p| match split_at(p, '@', s) {
+                Ok((limit, price)) => Ok(Block {
+                    limit: Some(limit.parse().map_err(|_| invalid_rate(s))?),
+                    price: price.parse()?,
+                }),
+                Err(_) => Ok(Block {
+                    limit: None,
+                    price: p.parse()?,
+                }),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Rate::Tiered(blocks))
+    }
+}
+
+fn split_at<'a>(part: &'a str, sep: char, whole: &str) -> Result<(&'a str, &'a str), Error> {
+    let mut halves = part.splitn(2, sep);
+    match (halves.next(), halves.next()) {
+        (Some(a), Some(b)) => Ok((a.trim(), b.trim())),
+        _ => Err(invalid_rate(whole)),
+    }
+}
+
+fn invalid_rate(s: &str) -> Error {
+    Error::InvalidTariff(format!("{:?} is not a rate", s))
+}
+
+impl fmt::Display for UnitPrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = format!("{:04}", self.0 % 10_000);
+        let digits = digits.trim_end_matches('0');
+        write!(f, "${}.{:0<2}", self.0 / 10_000, digits)
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rate::Flat(price) => write!(f, "{}", price),
+            Rate::Tiered(blocks) => {
+                let blocks: Vec<String> = blocks
+                    .iter()
+                    .map(|b| match b.limit {
+                        Some(limit) => format!("{}/day at {}", limit, b.price),
+                        None => format!("then {}", b.price),
+                    })
+                    .collect();
+                write!(f, "{}", blocks.join(", "))
+            }
+            Rate::TimeOfUse(bands) => {
+                let bands: Vec<String> = bands
+                    .iter()
+                    .map(|b| format!("{} {}% at {}", &b.name, b.share, b.price))
+                    .collect();
+                write!(f, "{}", bands.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Tariff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} for {}: {}/day supply, {}",
+            self.utility,
+            match self.site {
+                Some(site) => format!("site {}", site),
+                None => "all sites".to_string(),
+            },
+            self.supply,
+            self.rate
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Meter, Reading};
+    use crate::testing::{day, fixture};
+
+    fn rate(s: &str) -> Rate {
+        s.parse().unwrap()
+    }
+
+    fn tariff(rate: Rate) -> Tariff {
+        Tariff {
+            utility: Utility::Electricity,
+            site: None,
+            supply: Money::ZERO,
+            rate,
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_unit_prices() {
+        for (text, price, written) in [
+            ("0.2834", 2834, "$0.2834"),
+            ("$1.5", 15000, "$1.50"),
+            (".05", 500, "$0.05"),
+            ("2", 20000, "$2.00"),
+        ] {
+            let parsed: UnitPrice = text.parse().unwrap();
+            assert_eq!(parsed, UnitPrice(price));
+            assert_eq!(parsed.to_string(), written);
+            assert_eq!(written.parse(), Ok(parsed));
+        }
+        for text in ["", ".", "0.12345", "1.2.3", "-1", "abc", "922337203685478"] {
+            assert!(text.parse::<UnitPrice>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn reports_costs_too_large_to_hold() {
+        assert_eq!(UnitPrice(2).times(u64::MAX), Err(Error::Overflow));
+        assert_eq!(
+            rate("1000000").price(u64::MAX / 10, 1),
+            Err(Error::Overflow)
+        );
+    }
+
+    #[test]
+    fn prices_tiers_by_daily_limit() {
+        let lines = rate("10@0.25, 0.30").price(50, 2).unwrap();
+        assert_eq!(
+            lines,
+            [
+                (
+                    "block 1, 20 units at $0.25".to_string(),
+                    Money::from_units(5)
+                ),
+                (
+                    "block 2, 30 units at $0.30".to_string(),
+                    Money::from_units(9)
+                ),
+            ]
+        );
+        assert_eq!(rate("10@0.25, 0.30").price(15, 2).unwrap().len(), 1);
+        assert_eq!(rate("10@0.25, 0.30").price(50, u64::MAX).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn prices_time_of_use_by_share() {
+        let lines = rate("peak 40%@0.35, off-peak 60%@0.20")
+            .price(101, 7)
+            .unwrap();
+        assert_eq!(
+            lines,
+            [
+                ("peak, 40 units at $0.35".to_string(), Money::from_units(14)),
+                (
+                    "off-peak, 61 units at $0.20".to_string(),
+                    Money::from_cents(1220)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_rates_leaving_usage_unpriced() {
+        assert!(tariff(rate("10@0.25, 0.30")).check().is_ok());
+        assert!(tariff(rate("peak 40%@0.35, off-peak 60%@0.20"))
+            .check()
+            .is_ok());
+        assert_eq!(
+            tariff(rate("10@0.25, 20@0.30")).check(),
+            Err(Error::InvalidTariff(
+                "the last block must have no limit".into()
+            ))
+        );
+        assert_eq!(
+            tariff(rate("peak 40%@0.35, off-peak 50%@0.20")).check(),
+            Err(Error::InvalidTariff("band shares must total 100%".into()))
+        );
+    }
+
+    #[test]
+    fn charges_supply_once_per_utility() {
+        let (mut state, at) = fixture("");
+        let site = state.lease(at).unwrap().site;
+        for name in ["House", "Shed"] {
+            let meter = Meter {
+                name: name.into(),
+                utility: Utility::Electricity,
+                readings: vec![],
+            };
+            state.add_meter(site, meter).unwrap();
+            for (on, value) in [(day(1, 1), 100), (day(1, 7), 106)] {
+                state
+                    .record_reading(site, name, Reading { on, value })
+                    .unwrap();
+            }
+        }
+        state
+            .add_tariff(Tariff {
+                utility: Utility::Electricity,
+                site: None,
+                supply: Money::from_cents(100),
+                rate: "0.50".parse().unwrap(),
+            })
+            .unwrap();
+
+        let lines: Vec<_> = state
+            .utility_charges(at, day(1, 1), day(1, 7))
+            .unwrap()
+            .into_iter()
+            .map(|l| (l.description, l.amount))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("Supply, 7 days at $1.00".to_string(), Money::from_units(7)),
+                (
+                    "House usage, 6 units at $0.50".to_string(),
+                    Money::from_units(3)
+                ),
+                (
+                    "Shed usage, 6 units at $0.50".to_string(),
+                    Money::from_units(3)
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Utility meters, their readings, and the consumption between them.
+
+use super::{Error, LeaseId, Site, SiteId, State};
+use chrono::{Duration, NaiveDate as Date};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use strum_macros::EnumIter;
+
+/// Utility is a metered service supplied to a site.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Hash, Eq, Debug, Default, EnumIter)]
+pub enum Utility {
+    #[default]
+    Electricity,
+    Water,
+    Gas,
+}
+
+/// Meter measures one utility at a site. The name distinguishes meters of
+/// the same utility on a site, such as "House" and "Shed".
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug, Default)]
+pub struct Meter {
+    pub name: String,
+    pub utility: Utility,
+    /// Readings in date order.
+    #[serde(default)]
+    pub readings: Vec<Reading>,
+}
+
+/// Reading is the cumulative value shown on a meter on a given day.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Hash, Eq, Debug)]
+pub struct Reading {
+    pub on: Date,
+    pub value: u64,
+}
+
+/// Usage is consumption on a meter for the days `from` through `to`,
+/// attributed to the lease active over them, if any.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Usage {
+    pub meter: String,
+    pub utility: Utility,
+    pub lease: Option<LeaseId>,
+    pub from: Date,
+    pub to: Date,
+    pub units: u64,
+}
+
+impl State {
+    /// add_meter installs a meter on a site; names are unique per site.
+    pub fn add_meter(&mut self, site: SiteId, meter: Meter) -> Result<(), Error> {
+        let site = self.sites.get_mut(&site).ok_or(Error::UnknownSite(site))?;
+        if site.meter(&meter.name).is_some() {
+            return Err(Error::DuplicateMeter(meter.name));
+        }
+        site.meters.push(meter);
+        Ok(())
+    }
+
+    /// record_reading appends a reading to a meter. Readings must be entered
+    /// in date order and can never go backwards.
+    pub fn record_reading(
+        &mut self,
+        site: SiteId,
+        meter_name: &str,
+        reading: Reading,
+    ) -> Result<(), Error> {
+        let meter = self
+            .sites
+            .get_mut(&site)
+            .ok_or(Error::UnknownSite(site))?
+            .meters
+            .iter_mut()
+            .find(|m| m.name == meter_name)
+            .ok_or_else(|| Error::UnknownMeter(meter_name.to_string()))?;
+        if let Some(last) = meter.readings.last() {
+            if reading.on <= last.on || reading.value < last.value {
+                return Err(Error::ReadingOutOfOrder(last.clone()));
+            }
+        }
+        meter.readings.push(reading);
+        Ok(())
+    }
+
+    /// lease_usage is all metered consumption attributed to a lease.
+    pub fn lease_usage(&self, at: LeaseId) -> Vec<Usage> {
+        self.lease(at)
+            .and_then(|l| self.site(l.site))
+            .map(|s| s.usage())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|u| u.lease == Some(at))
+            .collect()
+    }
+}
+
+impl Site {
+    pub fn meter(&self, name: &str) -> Option<&Meter> {
+        self.meters.iter().find(|m| m.name == name)
+    }
+
+    /// usage is the consumption between each pair of consecutive readings on
+    /// every meter. Where the days between readings span more than one lease,
+    /// or vacant days, the consumption is split between them by days.
+    pub fn usage(&self) -> Vec<Usage> {
+        let mut usage = vec![];
+        for meter in &self.meters {
+            for pair in meter.readings.windows(2) {
+                let (prev, next) = (&pair[0], &pair[1]);
+                // The earlier reading was taken at the end of its day, so
+                // consumption starts the day after.
+                let from = prev.on + Duration::days(1);
+                let to = next.on;
+                let total_days = (to - from).num_days() as u64 + 1;
+                let total_units = next.value - prev.value;
+
+                let spans = self.occupancy(from, to);
+                let mut attributed = 0;
+                for (ii, (lease, span_from, span_to)) in spans.iter().enumerate() {
+                    let days = (*span_to - *span_from).num_days() as u64 + 1;
+                    // The last span takes the remainder so nothing is lost to
+                    // rounding.
+                    let units = if ii == spans.len() - 1 {
+                        total_units - attributed
+                    } else {
+                        // Widened so the product cannot overflow; the share
+                        // is at most `total_units`, so it fits back.
+                        (u128::from(total_units) * u128::from(days) / u128::from(total_days)) as u64
+                    };
+                    attributed += units;
+                    usage.push(Usage {
+                        meter: meter.name.clone(),
+                        utility: meter.utility,
+                        lease: *lease,
+                        from: *span_from,
+                        to: *span_to,
+                        units,
+                    });
+                }
+            }
+        }
+        usage
+    }
+
+    // occupancy splits `from..=to` into runs of days with the same active
+    // lease, or none.
+    fn occupancy(&self, from: Date, to: Date) -> Vec<(Option<LeaseId>, Date, Date)> {
+        let mut spans: Vec<(Option<LeaseId>, Date, Date)> = vec![];
+        let mut day = from;
+        while day <= to {
+            let lease = self.active_lease(day).map(|l| l.id);
+            match spans.last_mut() {
+                Some((current, _, end)) if *current == lease => *end = day,
+                _ => spans.push((lease, day, day)),
+            }
+            day += Duration::days(1);
+        }
+        spans
+    }
+}
+
+impl Utility {
+    /// unit is what the meter counts.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Utility::Electricity => "kWh",
+            Utility::Water => "L",
+            Utility::Gas => "MJ",
+        }
+    }
+}
+
+impl fmt::Display for Utility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Utility::Electricity => "Electricity",
+                Utility::Water => "Water",
+                Utility::Gas => "Gas",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", &self.name, self.utility)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Lease, TenantId};
+    use crate::testing::{day, lease, site, tenant};
+
+    // metered is state with site 7, metered for electricity, and tenants Jo
+    // and Sam.
+    fn metered() -> (State, SiteId, TenantId, TenantId) {
+        let mut state = State::default();
+        let jo = state.register_tenant(tenant("Jo")).unwrap();
+        let sam = state.register_tenant(tenant("Sam")).unwrap();
+        let seven = state.list_site(site("7")).unwrap();
+        let meter = Meter {
+            name: "House".into(),
+            utility: Utility::Electricity,
+            readings: vec![],
+        };
+        state.add_meter(seven, meter).unwrap();
+        (state, seven, jo, sam)
+    }
+
+    // active puts a lease in force from its start.
+    fn active(state: &mut State, lease: Lease) -> LeaseId {
+        let start = lease.term.start;
+        let at = state.lease_site(lease).unwrap();
+        state.activate_lease(at, start).unwrap();
+        at
+    }
+
+    fn read(state: &mut State, site: SiteId, on: Date, value: u64) {
+        state
+            .record_reading(site, "House", Reading { on, value })
+            .unwrap();
+    }
+
+    fn usage(lease: Option<LeaseId>, from: Date, to: Date, units: u64) -> Usage {
+        Usage {
+            meter: "House".into(),
+            utility: Utility::Electricity,
+            lease,
+            from,
+            to,
+            units,
+        }
+    }
+
+    #[test]
+    fn splits_usage_between_leases_by_days() {
+        let (mut state, seven, jo, sam) = metered();
+        let first = active(&mut state, lease(jo, seven, day(1, 1), 14));
+        let second = active(&mut state, lease(sam, seven, day(1, 15), 14));
+        read(&mut state, seven, day(1, 10), 1000);
+        read(&mut state, seven, day(1, 20), 1100);
+
+        assert_eq!(
+            state.site(seven).unwrap().usage(),
+            [
+                usage(Some(first), day(1, 11), day(1, 14), 40),
+                usage(Some(second), day(1, 15), day(1, 20), 60),
+            ]
+        );
+        assert_eq!(
+            state.lease_usage(second),
+            [usage(Some(second), day(1, 15), day(1, 20), 60)]
+        );
+    }
+
+    #[test]
+    fn leaves_usage_before_a_lease_starts_unattributed() {
+        let (mut state, seven, _, sam) = metered();
+        let at = active(&mut state, lease(sam, seven, day(1, 15), 14));
+        read(&mut state, seven, day(1, 12), 0);
+        read(&mut state, seven, day(1, 15), 100);
+
+        // The vacant days take their share rounded down; the lease takes
+        // the remainder.
+        assert_eq!(
+            state.site(seven).unwrap().usage(),
+            [
+                usage(None, day(1, 13), day(1, 14), 66),
+                usage(Some(at), day(1, 15), day(1, 15), 34),
+            ]
+        );
+    }
+}
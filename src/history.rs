@@ -0,0 +1,261 @@
+//! Undo and redo of the changes made in a session.
+//!
+//! Each step keeps the records its change made, as they were before and
+//! after. Undoing a step logs an event putting back those records as they
+//! were before, and redoing it one making them as they were after, so the
+//! log and the audit trail keep the change, its undoing and its redoing.
+//! Changes made since that are not steps, such as rent falling due, are
+//! left alone.
+
+use crate::domain::{audit, Change, Event, State};
+use crate::store::Store;
+use std::error::Error;
+
+/// DEPTH is how many steps can be undone.
+pub const DEPTH: usize = 50;
+
+/// Step is a change that can be undone.
+pub struct Step {
+    /// What the change did, as it was described when it was made.
+    pub description: String,
+    changes: Vec<Change>,
+}
+
+#[derive(Default)]
+pub struct History {
+    done: Vec<Step>,
+    undone: Vec<Step>,
+}
+
+impl History {
+    /// record makes the change an event records and logs it as a step that
+    /// can be undone. Recording a change forgets the steps that were undone.
+    pub fn record(
+        &mut self,
+        store: &mut impl Store,
+        state: &mut State,
+        event: Event,
+        actor: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let changes = store.record(state, event.clone(), actor)?;
+        if !changes.is_empty() {
+            self.push(event.describe(state), changes);
+            self.undone.clear();
+        }
+        Ok(())
+    }
+
+    /// undo puts the records the latest step changed back as they were
+    /// before it.
+    pub fn undo(
+        &mut self,
+        store: &mut impl Store,
+        state: &mut State,
+        actor: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let reverted = match self.done.last() {
+            Some(step) => Event::Reverted {
+                undid: step.description.clone(),
+                changes: audit::reverse(step.changes.clone()),
+            },
+            None => return Ok(()),
+        };
+        store.record(state, reverted, actor)?;
+        if let Some(step) = self.done.pop() {
+            self.undone.push(step);
+        }
+        Ok(())
+    }
+
+    /// redo makes the records the latest step undone changed as they were
+    /// after it.
+    pub fn redo(
+        &mut self,
+        store: &mut impl Store,
+        state: &mut State,
+        actor: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let redone = match self.undone.last() {
+            Some(step) => Event::Redone {
+                redid: step.description.clone(),
+                changes: step.changes.clone(),
+            },
+            None => return Ok(()),
+        };
+        store.record(state, redone, actor)?;
+        if let Some(step) = self.undone.pop() {
+            self.push(step.description, step.changes);
+        }
+        Ok(())
+    }
+
+    /// done are the steps that can be undone, oldest first.
+    pub fn done(&self) -> &[Step] {
+        &self.done
+    }
+
+    /// undone are the steps that can be redone, the next to redo first.
+    pub fn undone(&self) -> impl Iterator<Item = &Step> {
+        self.undone.iter().rev()
+    }
+
+    fn push(&mut self, description: String, changes: Vec<Change>) {
+        if self.done.len() == DEPTH {
+            self.done.remove(0);
+        }
+        self.done.push(Step {
+            description,
+            changes,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Method, Money, Payment, Tenant, TenantId, Towards};
+    use crate::store::Memory;
+    use crate::testing::{day, fixture};
+
+    fn tenant(name: &str) -> Event {
+        Event::TenantRegistered(Tenant {
+            id: Default::default(),
+            name: name.into(),
+            contact: Default::default(),
+        })
+    }
+
+    #[test]
+    fn undoes_and_redoes_steps() {
+        let mut store = Memory::default();
+        let mut state = State::default();
+        let mut history = History::default();
+        history
+            .record(&mut store, &mut state, tenant("Jo"), "manager")
+            .unwrap();
+        history
+            .record(&mut store, &mut state, tenant("Sam"), "manager")
+            .unwrap();
+        let both = state.clone();
+
+        history.undo(&mut store, &mut state, "manager").unwrap();
+        assert!(state.tenant_named("Sam").is_none());
+        assert_eq!(store.load().unwrap().as_ref(), Some(&state));
+        let undone: Vec<_> = history.undone().map(|s| &s.description).collect();
+        assert_eq!(undone, ["Registered tenant Sam"]);
+
+        history.redo(&mut store, &mut state, "manager").unwrap();
+        assert_eq!(state, both);
+        assert_eq!(store.load().unwrap(), Some(both));
+        assert_eq!(history.done().len(), 2);
+
+        history.undo(&mut store, &mut state, "manager").unwrap();
+        history
+            .record(&mut store, &mut state, tenant("Al"), "manager")
+            .unwrap();
+        assert!(history.undone().next().is_none());
+    }
+
+    #[test]
+    fn never_reissues_ids_undone() {
+        let mut store = Memory::default();
+        let mut state = State::default();
+        let mut history = History::default();
+        history
+            .record(&mut store, &mut state, tenant("Jo"), "manager")
+            .unwrap();
+        let jo = state.tenant_named("Jo").unwrap().id;
+
+        history.undo(&mut store, &mut state, "manager").unwrap();
+        history
+            .record(&mut store, &mut state, tenant("Sam"), "manager")
+            .unwrap();
+        assert_ne!(state.tenant_named("Sam").unwrap().id, jo);
+        assert_eq!(store.load().unwrap(), Some(state));
+    }
+
+    #[test]
+    fn undoes_changes_to_leases_and_the_ledger() {
+        let (mut state, at) = fixture("jo@example.com");
+        let mut store = Memory::default();
+        store.save(&state).unwrap();
+        let before = state.clone();
+        let mut history = History::default();
+        for event in [
+            Event::PaymentRecorded(Payment {
+                tenant: TenantId(1),
+                lease: at,
+                amount: Money::from_units(100),
+                on: day(1, 2),
+                method: Method::Cash,
+                reference: "".into(),
+                towards: Towards::Rent,
+            }),
+            Event::InvoiceIssued {
+                lease: at,
+                on: day(1, 3),
+            },
+            Event::LeaseTerminated {
+                lease: at,
+                end: day(1, 10),
+                reason: "left".into(),
+                on: day(1, 10),
+            },
+        ] {
+            history
+                .record(&mut store, &mut state, event, "manager")
+                .unwrap();
+        }
+
+        for _ in 0..3 {
+            history.undo(&mut store, &mut state, "manager").unwrap();
+        }
+        assert_eq!(state, before);
+        assert_eq!(store.load().unwrap(), Some(before));
+        // Undoing logs the records it puts back, not the whole of state.
+        assert!(!store.log.iter().any(|e| e.contains("Avisha Holiday Park")));
+    }
+
+    #[test]
+    fn undoes_only_the_step_itself() {
+        let (mut state, at) = fixture("jo@example.com");
+        let mut store = Memory::default();
+        store.save(&state).unwrap();
+        let mut history = History::default();
+        let payment = Event::PaymentRecorded(Payment {
+            tenant: TenantId(1),
+            lease: at,
+            amount: Money::from_units(100),
+            on: day(1, 2),
+            method: Method::Cash,
+            reference: "".into(),
+            towards: Towards::Rent,
+        });
+        history
+            .record(&mut store, &mut state, payment, "manager")
+            .unwrap();
+        // Rent falls due between the payment and its undoing.
+        store
+            .record(&mut state, Event::RentFellDue { on: day(1, 8) }, "manager")
+            .unwrap();
+        let paid = state.clone();
+        let owing = state.owing(at, Towards::Rent, day(1, 8));
+
+        history.undo(&mut store, &mut state, "manager").unwrap();
+        assert!(state.payments.is_empty());
+        assert_eq!(
+            state.ledger.postings().len(),
+            paid.ledger.postings().len() - 1
+        );
+        assert_eq!(
+            state.owing(at, Towards::Rent, day(1, 8)),
+            owing + Money::from_units(100)
+        );
+        assert_eq!(store.load().unwrap().as_ref(), Some(&state));
+
+        history.redo(&mut store, &mut state, "manager").unwrap();
+        assert_eq!(state.payments, paid.payments);
+        assert_eq!(state.owing(at, Towards::Rent, day(1, 8)), owing);
+        assert_eq!(store.load().unwrap(), Some(state));
+    }
+}
@@ -1,7 +1,11 @@
-use crate::app::State as AppState;
-use crate::app::{Site, Tenant};
+use crate::domain::{
+    Frequency, Lease, LeaseId, Money, Site, State as AppState, Status, Tenant, Term,
+    UtilityAccount, MAX_TERM,
+};
 use crate::form;
+use crate::validate::Validate;
 
+use chrono::Local;
 use std::collections::HashMap;
 use std::default::Default;
 use std::mem;
@@ -14,9 +18,12 @@ use yew::services::ConsoleService;
 use yew_components::Select;
 use yewtil::NeqAssign;
 
-pub struct Form {
+pub struct Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
     pub link: ComponentLink<Self>,
-    pub props: Props,
+    pub props: Props<V>,
     pub model: Model,
     pub errors: HashMap<String, String>,
 }
@@ -28,12 +35,17 @@ pub struct Model {
     pub start: String,
     pub duration: String,
     pub rent: String,
+    pub frequency: Frequency,
 }
 
 #[derive(Properties, Clone, PartialEq)]
-pub struct Props {
+pub struct Props<V>
+where
+    V: Validate + Clone,
+{
     pub submit: Callback<Model>,
     pub state: AppState,
+    pub validator: V,
 }
 
 pub enum Msg {
@@ -44,15 +56,19 @@ pub enum Msg {
 
 pub enum Field {
     Site(Site),
-    Tenant(Tenant),
+    Tenant(Box<Tenant>),
     Start(String),
     Duration(String),
     Rent(String),
+    Frequency(Frequency),
 }
 
-impl Component for Form {
+impl<V> Component for Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
     type Message = Msg;
-    type Properties = Props;
+    type Properties = Props<V>;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         Form {
@@ -72,25 +88,21 @@ impl Component for Form {
             Msg::Edit(field) => {
                 match field {
                     Field::Site(v) => self.model.site = v,
-                    Field::Tenant(v) => self.model.tenant = v,
+                    Field::Tenant(v) => self.model.tenant = *v,
                     Field::Start(v) => self.model.start = v,
                     Field::Duration(v) => self.model.duration = v,
                     Field::Rent(v) => self.model.rent = v,
+                    Field::Frequency(v) => self.model.frequency = v,
                 };
-                // self.validate_edit();
+                self.validate_edit();
             }
             Msg::Submit => {
-                // self.validate();
-                // if self.props.validator.validate(&self.model).is_ok() {
-                //     self.props
-                //         .submit
-                //         .emit(mem::replace(&mut self.model, Model::default()));
-                // }
-                self.props
-                    .submit
-                    .emit(mem::replace(&mut self.model, Model::default()));
+                self.validate();
+                if self.props.validator.validate(&self.model).is_ok() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
             }
-            Msg::Nope | _ => {}
+            Msg::Nope => {}
         };
 
         true
@@ -106,12 +118,29 @@ impl Component for Form {
             self.errors.get(field_name).map(|s| s.to_string())
         };
 
+        // Only offer sites free for the requested term, or on its start date
+        // until a duration is entered.
+        let term = Term {
+            start: self
+                .model
+                .start
+                .parse()
+                .unwrap_or_else(|_| Local::now().date_naive()),
+            duration: self
+                .model
+                .duration
+                .parse()
+                .ok()
+                .filter(|d| (1..=MAX_TERM).contains(d))
+                .unwrap_or(1),
+            rent: Money::ZERO,
+            frequency: self.model.frequency,
+        };
         let sites = self
             .props
             .state
-            .sites
-            .values()
-            .map(|s| s.clone())
+            .free_sites(&term)
+            .cloned()
             .collect::<Vec<Site>>();
 
         let tenants = self
@@ -119,7 +148,7 @@ impl Component for Form {
             .state
             .tenants
             .values()
-            .map(|t| t.clone())
+            .cloned()
             .collect::<Vec<Tenant>>();
 
         html! {
@@ -142,7 +171,7 @@ impl Component for Form {
                     error=get_error("tenant")
                 >
                     <Select<Tenant>
-                        on_change=self.link.callback(|v| Msg::Edit(Field::Tenant(v)))
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Tenant(Box::new(v))))
                         options=tenants
                         selected=&self.model.tenant
                     />
@@ -175,11 +204,23 @@ impl Component for Form {
                 </form::Field>
 
                 <form::Field
-                    label={"Rent (fortnightly)"}
+                    label={"Rent Frequency"}
+                    error=get_error("frequency")
+                >
+                    <Select<Frequency>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Frequency(v)))
+                        options=Frequency::iter().collect::<Vec<_>>()
+                        selected=&self.model.frequency
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={format!("Rent (per {})", self.model.frequency.per())}
                     error=get_error("rent")
                 >
                     <input
-                        type="number"
+                        type="text"
+                        placeholder="0.00"
                         value=&self.model.rent
                         oninput=self.link.callback(|v: InputData| {
                             Msg::Edit(Field::Rent(v.value))
@@ -197,3 +238,84 @@ impl Component for Form {
         }
     }
 }
+
+impl<V> Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    fn validate(&mut self) {
+        match self.props.validator.validate(&self.model) {
+            Err(errors) => self.errors = errors,
+            Ok(_) => self.errors.clear(),
+        };
+    }
+
+    // validate_edit ignores validation for fields not yet filled in.
+    fn validate_edit(&mut self) {
+        self.validate();
+
+        {
+            if self.model.site.number.is_empty() {
+                self.errors.remove("site");
+            }
+            if self.model.tenant.name.is_empty() {
+                self.errors.remove("tenant");
+            }
+            if self.model.start.is_empty() {
+                self.errors.remove("start");
+            }
+            if self.model.duration.is_empty() {
+                self.errors.remove("duration");
+            }
+            if self.model.rent.is_empty() {
+                self.errors.remove("rent");
+            }
+        }
+    }
+}
+
+impl Model {
+    /// lease parses the raw form input into a domain `Lease`, keyed by field
+    /// name on failure.
+    pub fn lease(&self) -> Result<Lease, HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        let start = self.start.parse().ok();
+        if start.is_none() {
+            errors.insert("start".into(), "must be a date".into());
+        }
+        let duration = self.duration.parse().ok().filter(|d| *d <= MAX_TERM);
+        if duration.is_none() {
+            errors.insert(
+                "duration".into(),
+                format!("must be a number of days up to {}", MAX_TERM),
+            );
+        }
+        let rent = self.rent.parse().ok();
+        if rent.is_none() {
+            errors.insert(
+                "rent".into(),
+                format!("must be an amount up to {}", Money::MAX),
+            );
+        }
+
+        match (start, duration, rent) {
+            (Some(start), Some(duration), Some(rent)) => Ok(Lease {
+                id: LeaseId::default(),
+                tenant: self.tenant.id,
+                site: self.site.id,
+                term: Term {
+                    start,
+                    duration,
+                    rent,
+                    frequency: self.frequency,
+                },
+                status: Status::Draft,
+                history: vec![],
+                renews: None,
+                utility: UtilityAccount::default(),
+            }),
+            _ => Err(errors),
+        }
+    }
+}
@@ -2,17 +2,52 @@
 #![recursion_limit = "1024"]
 
 mod app;
+mod bill_form;
+mod business_form;
+pub mod domain;
 mod form;
+mod history;
 mod lease_form;
+pub mod mail;
+mod meter_form;
+mod payment_form;
+pub mod pdf;
+mod reading_form;
+mod rename_form;
+mod renew_form;
 mod site_form;
+pub mod sms;
+pub mod store;
+mod tariff_form;
 mod tenant_form;
+mod terminate_form;
+#[cfg(test)]
+mod testing;
 mod validate;
 
+use std::cell::RefCell;
+use std::rc::Rc;
+use store::{Memory, Store};
 use wasm_bindgen::prelude::*;
 
+/// run_app starts the app, saving to the store named, which is one of
+/// "local", "indexeddb" or "memory". Local storage is used if none is named.
+/// Changes are recorded in the audit trail as made by `actor`.
 #[wasm_bindgen]
-pub fn run_app() -> Result<(), JsValue> {
-    yew::start_app::<app::App>();
+pub async fn run_app(store: Option<String>, actor: Option<String>) -> Result<(), JsValue> {
+    let mut errors = vec![];
+    let store: Box<dyn Store> = match store::open(store.as_deref().unwrap_or("local")).await {
+        Ok(store) => store,
+        Err(err) => {
+            errors.push(format!("Could not open storage: {}. Changes will not be saved.", err));
+            Box::new(Memory::default())
+        }
+    };
+    yew::start_app_with_props::<app::App>(app::Props {
+        store: Rc::new(RefCell::new(store)),
+        errors,
+        actor: actor.unwrap_or_default(),
+    });
 
     Ok(())
 }
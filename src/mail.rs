@@ -0,0 +1,269 @@
+//! Mail delivery of invoices.
+//!
+//! The browser cannot speak SMTP, so mail is sent from the command line (see
+//! `src/bin/avisha-mail.rs`) against state exported from the app. Transports
+//! are pluggable: SMTP for real delivery, a directory of `.eml` files for
+//! checking output by hand, and memory for tests.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod smtp;
+
+use crate::domain::{Channel, Delivery, DeliveryStatus, Invoice, State, TenantId};
+use crate::pdf;
+use chrono::NaiveDate as Date;
+use std::fmt;
+
+/// Message is an email ready to hand to a transport.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Message {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Transport sends messages.
+pub trait Transport {
+    fn send(&mut self, message: &Message) -> Result<(), Error>;
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// NoSender is a business with no email address to send from.
+    NoSender,
+    /// NoAddress names a tenant with no email address.
+    NoAddress(String),
+    /// NotByEmail names a tenant who would rather not be emailed.
+    NotByEmail(String),
+    InvalidAddress(String),
+    Transport(String),
+}
+
+/// Memory keeps sent messages, for tests.
+#[derive(Default, Debug)]
+pub struct Memory {
+    pub sent: Vec<Message>,
+}
+
+impl Transport for Memory {
+    fn send(&mut self, message: &Message) -> Result<(), Error> {
+        self.sent.push(message.clone());
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Message {
+    /// to_mime builds the message as it goes over the wire.
+    pub fn to_mime(&self) -> Result<lettre::Message, Error> {
+        use lettre::message::{header::ContentType, Attachment as Part, MultiPart, SinglePart};
+
+        let mailbox = |address: &str| {
+            address
+                .parse::<lettre::message::Mailbox>()
+                .map_err(|_| Error::InvalidAddress(address.to_string()))
+        };
+        let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(self.body.clone()));
+        for attachment in &self.attachments {
+            let content_type = ContentType::parse(&attachment.content_type)
+                .map_err(|err| Error::Transport(err.to_string()))?;
+            parts = parts.singlepart(
+                Part::new(attachment.filename.clone()).body(attachment.data.clone(), content_type),
+            );
+        }
+        lettre::Message::builder()
+            .from(mailbox(&self.from)?)
+            .to(mailbox(&self.to)?)
+            .subject(self.subject.clone())
+            .multipart(parts)
+            .map_err(|err| Error::Transport(err.to_string()))
+    }
+}
+
+/// invoice_message is the email carrying an invoice to its tenant, with the
+/// invoice attached as a PDF. Tenants who prefer another channel are not
+/// emailed.
+pub fn invoice_message(state: &State, invoice: &Invoice) -> Result<Message, Error> {
+    let business = &state.business;
+    if business.email.trim().is_empty() {
+        return Err(Error::NoSender);
+    }
+    let tenant_name = state.tenant_name(invoice.tenant);
+    if !by_email(state, invoice.tenant) {
+        return Err(Error::NotByEmail(tenant_name.to_string()));
+    }
+    let to = state
+        .tenant(invoice.tenant)
+        .and_then(|t| t.contact.email())
+        .ok_or_else(|| Error::NoAddress(tenant_name.to_string()))?;
+    let site_number = state
+        .lease(invoice.lease)
+        .map_or("", |l| state.site_number(l.site));
+
+    let mut body = format!(
+        "Hi {},\n\n\
+         Please find attached invoice #{} for site {}, covering {} to {}.\n\
+         The amount due is {}, payable by {}.\n",
+        tenant_name,
+        invoice.number,
+        site_number,
+        invoice.from,
+        invoice.to,
+        invoice.amount_due(),
+        invoice.due
+    );
+    if !business.payment_instructions.trim().is_empty() {
+        body.push_str(&format!(
+            "\nHow to pay:\n{}\n",
+            business.payment_instructions.trim()
+        ));
+    }
+    body.push_str(&format!("\nThank you,\n{}\n", &business.name));
+
+    Ok(Message {
+        from: format!("{} <{}>", &business.name, business.email.trim()),
+        to: to.to_string(),
+        subject: format!("Invoice #{} from {}", invoice.number, &business.name),
+        body,
+        attachments: vec![Attachment {
+            filename: format!("invoice-{}.pdf", invoice.number),
+            content_type: "application/pdf".into(),
+            data: pdf::invoice::invoice(state, invoice).to_bytes(),
+        }],
+    })
+}
+
+/// deliver_invoices sends every invoice not yet delivered to a tenant who
+/// prefers email and records the outcome on each. Failed invoices are tried
+/// again next time.
+pub fn deliver_invoices(
+    state: &mut State,
+    transport: &mut dyn Transport,
+    on: Date,
+) -> Vec<(u32, DeliveryStatus)> {
+    let pending: Vec<u32> = state
+        .invoices
+        .iter()
+        .filter(|i| !i.is_delivered() && by_email(state, i.tenant))
+        .map(|i| i.number)
+        .collect();
+    let mut outcomes = vec![];
+    for number in pending {
+        let invoice = match state.invoices.iter().find(|i| i.number == number) {
+            Some(invoice) => invoice,
+            None => continue,
+        };
+        let (to, result) = match invoice_message(state, invoice) {
+            Ok(message) => (message.to.clone(), transport.send(&message)),
+            Err(err) => (String::new(), Err(err)),
+        };
+        let status = match result {
+            Ok(()) => DeliveryStatus::Sent,
+            Err(err) => DeliveryStatus::Failed(err.to_string()),
+        };
+        let delivery = Delivery {
+            on,
+            to,
+            status: status.clone(),
+        };
+        // The invoice was found above.
+        let _ = state.record_delivery(number, delivery);
+        outcomes.push((number, status));
+    }
+    outcomes
+}
+
+// by_email reports whether a tenant would rather be contacted by email.
+fn by_email(state: &State, tenant: TenantId) -> bool {
+    state
+        .tenant(tenant)
+        .is_some_and(|t| t.contact.preferred == Channel::Email)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoSender => write!(f, "the business has no email address to send from"),
+            Error::NoAddress(name) => write!(f, "tenant {:?} has no email address", name),
+            Error::NotByEmail(name) => write!(f, "tenant {:?} prefers not to be emailed", name),
+            Error::InvalidAddress(address) => write!(f, "{:?} is not a valid address", address),
+            Error::Transport(reason) => write!(f, "sending failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{day, fixture};
+
+    #[test]
+    fn delivers_each_invoice_once() {
+        let (mut state, at) = fixture("jo@example.com");
+        state.issue_invoice(at, day(1, 1)).unwrap();
+        let mut transport = Memory::default();
+
+        let outcomes = deliver_invoices(&mut state, &mut transport, day(1, 2));
+        assert_eq!(outcomes, vec![(1, DeliveryStatus::Sent)]);
+        assert_eq!(transport.sent.len(), 1);
+        assert_eq!(transport.sent[0].to, "jo@example.com");
+        assert_eq!(transport.sent[0].attachments[0].filename, "invoice-1.pdf");
+        assert!(state.invoices[0].is_delivered());
+
+        let outcomes = deliver_invoices(&mut state, &mut transport, day(1, 3));
+        assert!(outcomes.is_empty());
+        assert_eq!(transport.sent.len(), 1);
+    }
+
+    #[test]
+    fn records_failure_without_email() {
+        let (mut state, at) = fixture("0400 000 000");
+        for tenant in state.tenants.values_mut() {
+            tenant.contact.preferred = Channel::Email;
+        }
+        state.issue_invoice(at, day(1, 1)).unwrap();
+        let mut transport = Memory::default();
+
+        let outcomes = deliver_invoices(&mut state, &mut transport, day(1, 2));
+        assert_eq!(
+            outcomes,
+            vec![(
+                1,
+                DeliveryStatus::Failed(Error::NoAddress("Jo Citizen".into()).to_string())
+            )]
+        );
+        assert!(transport.sent.is_empty());
+        assert!(!state.invoices[0].is_delivered());
+        assert_eq!(state.invoices[0].deliveries.len(), 1);
+    }
+
+    #[test]
+    fn emails_only_tenants_who_prefer_it() {
+        let (mut state, at) = fixture("jo@example.com, 0400 000 000");
+        for tenant in state.tenants.values_mut() {
+            tenant.contact.preferred = Channel::Sms;
+        }
+        state.issue_invoice(at, day(1, 1)).unwrap();
+        let mut transport = Memory::default();
+
+        let outcomes = deliver_invoices(&mut state, &mut transport, day(1, 2));
+        assert!(outcomes.is_empty());
+        assert!(state.invoices[0].deliveries.is_empty());
+        assert_eq!(
+            invoice_message(&state, &state.invoices[0]),
+            Err(Error::NotByEmail("Jo Citizen".into()))
+        );
+    }
+}
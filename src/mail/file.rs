@@ -0,0 +1,44 @@
+//! Delivery into a directory, one `.eml` file per message.
+
+use super::{Error, Message, Transport};
+use std::fs;
+use std::path::PathBuf;
+
+/// File writes each message to `dir` instead of sending it, so it can be
+/// opened in a mail client and checked by hand.
+pub struct File {
+    dir: PathBuf,
+    written: usize,
+}
+
+impl File {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|err| Error::Transport(err.to_string()))?;
+        Ok(File { dir, written: 0 })
+    }
+}
+
+impl Transport for File {
+    fn send(&mut self, message: &Message) -> Result<(), Error> {
+        let mime = message.to_mime()?;
+        self.written += 1;
+        let name = format!("{:04}-{}.eml", self.written, sanitise(&message.to));
+        fs::write(self.dir.join(name), mime.formatted())
+            .map_err(|err| Error::Transport(err.to_string()))
+    }
+}
+
+// sanitise keeps an address safe to use in a file name.
+fn sanitise(address: &str) -> String {
+    address
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '@' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
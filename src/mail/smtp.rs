@@ -0,0 +1,42 @@
+//! Delivery through an SMTP relay.
+
+use super::{Error, Message, Transport};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport as _};
+
+/// Smtp sends mail through a relay, over TLS unless told otherwise.
+pub struct Smtp {
+    transport: SmtpTransport,
+}
+
+impl Smtp {
+    /// new connects to `host` with STARTTLS on the submission port, logging
+    /// in when credentials are given.
+    pub fn new(host: &str, credentials: Option<(String, String)>) -> Result<Self, Error> {
+        let builder =
+            SmtpTransport::starttls_relay(host).map_err(|err| Error::Transport(err.to_string()))?;
+        let builder = match credentials {
+            Some((username, password)) => builder.credentials(Credentials::new(username, password)),
+            None => builder,
+        };
+        Ok(Smtp {
+            transport: builder.build(),
+        })
+    }
+
+    /// plain connects without encryption, such as to a relay on localhost.
+    pub fn plain(host: &str, port: u16) -> Self {
+        Smtp {
+            transport: SmtpTransport::builder_dangerous(host).port(port).build(),
+        }
+    }
+}
+
+impl Transport for Smtp {
+    fn send(&mut self, message: &Message) -> Result<(), Error> {
+        self.transport
+            .send(&message.to_mime()?)
+            .map(|_| ())
+            .map_err(|err| Error::Transport(err.to_string()))
+    }
+}
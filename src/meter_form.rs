@@ -0,0 +1,191 @@
+use crate::domain::{Meter, Site, State as AppState, Utility};
+use crate::form;
+use crate::validate::Validate;
+
+use std::collections::HashMap;
+use std::mem;
+use strum::IntoEnumIterator;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yew_components::Select;
+use yewtil::NeqAssign;
+
+pub struct Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    pub link: ComponentLink<Self>,
+    pub props: Props<V>,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Model {
+    pub site: Site,
+    pub name: String,
+    pub utility: Utility,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props<V>
+where
+    V: Validate + Clone,
+{
+    pub submit: Callback<Model>,
+    pub state: AppState,
+    pub validator: V,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    Site(Site),
+    Name(String),
+    Utility(Utility),
+}
+
+impl<V> Component for Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    type Message = Msg;
+    type Properties = Props<V>;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::Site(v) => self.model.site = v,
+                    Field::Name(v) => self.model.name = v,
+                    Field::Utility(v) => self.model.utility = v,
+                };
+                self.validate_edit();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.props.validator.validate(&self.model).is_ok() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        let sites = self
+            .props
+            .state
+            .sites
+            .values()
+            .cloned()
+            .collect::<Vec<Site>>();
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Site"}
+                    error=get_error("site")
+                >
+                    <Select<Site>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Site(v)))
+                        options=sites
+                        selected=&self.model.site
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Utility"}
+                    error=get_error("utility")
+                >
+                    <Select<Utility>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Utility(v)))
+                        options=Utility::iter().collect::<Vec<_>>()
+                        selected=&self.model.utility
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Meter Name"}
+                    error=get_error("name")
+                >
+                    <input
+                        type="text"
+                        placeholder="Main"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Name(v.value)))
+                        value=&self.model.name
+                    />
+                </form::Field>
+
+                <button
+                    type="submit"
+                    disabled={!self.errors.is_empty()}
+                >
+                    {"Install"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl<V> Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    fn validate(&mut self) {
+        match self.props.validator.validate(&self.model) {
+            Err(errors) => self.errors = errors,
+            Ok(_) => self.errors.clear(),
+        };
+    }
+
+    // validate_edit ignores validation for fields not yet filled in.
+    fn validate_edit(&mut self) {
+        self.validate();
+
+        {
+            if self.model.site.number.is_empty() {
+                self.errors.remove("site");
+            }
+            if self.model.name.is_empty() {
+                self.errors.remove("name");
+            }
+        }
+    }
+}
+
+impl Model {
+    pub fn meter(&self) -> Meter {
+        Meter {
+            name: self.name.trim().to_string(),
+            utility: self.utility,
+            readings: vec![],
+        }
+    }
+}
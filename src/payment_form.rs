@@ -0,0 +1,313 @@
+use crate::domain::{Lease, Method, Money, Payment, State as AppState, Tenant, Towards};
+use crate::form;
+use crate::validate::Validate;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use strum::IntoEnumIterator;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yew_components::Select;
+use yewtil::NeqAssign;
+
+pub struct Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    pub link: ComponentLink<Self>,
+    pub props: Props<V>,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Model {
+    pub tenant: Tenant,
+    pub lease: Option<LeaseChoice>,
+    pub amount: String,
+    pub on: String,
+    pub method: Method,
+    pub reference: String,
+    pub towards: Towards,
+}
+
+/// LeaseChoice is a lease of the selected tenant, shown by the site it is on.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LeaseChoice {
+    pub lease: Lease,
+    pub site_number: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props<V>
+where
+    V: Validate + Clone,
+{
+    pub submit: Callback<Model>,
+    pub state: AppState,
+    pub validator: V,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    Tenant(Box<Tenant>),
+    Lease(LeaseChoice),
+    Amount(String),
+    On(String),
+    Method(Method),
+    Reference(String),
+    Towards(Towards),
+}
+
+impl<V> Component for Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    type Message = Msg;
+    type Properties = Props<V>;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::Tenant(v) => {
+                        self.model.tenant = *v;
+                        self.model.lease = None;
+                    }
+                    Field::Lease(v) => self.model.lease = Some(v),
+                    Field::Amount(v) => self.model.amount = v,
+                    Field::On(v) => self.model.on = v,
+                    Field::Method(v) => self.model.method = v,
+                    Field::Reference(v) => self.model.reference = v,
+                    Field::Towards(v) => self.model.towards = v,
+                };
+                self.validate_edit();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.props.validator.validate(&self.model).is_ok() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        let tenants = self
+            .props
+            .state
+            .tenants
+            .values()
+            .cloned()
+            .collect::<Vec<Tenant>>();
+
+        let state = &self.props.state;
+        let leases = state
+            .leases()
+            .filter(|l| l.tenant == self.model.tenant.id)
+            .map(|l| LeaseChoice {
+                lease: l.clone(),
+                site_number: state.site_number(l.site).to_string(),
+            })
+            .collect::<Vec<LeaseChoice>>();
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Tenant"}
+                    error=get_error("tenant")
+                >
+                    <Select<Tenant>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Tenant(Box::new(v))))
+                        options=tenants
+                        selected=&self.model.tenant
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Lease"}
+                    error=get_error("lease")
+                >
+                    <Select<LeaseChoice>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Lease(v)))
+                        options=leases
+                        selected=self.model.lease.clone()
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Towards"}
+                    error=get_error("towards")
+                >
+                    <Select<Towards>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Towards(v)))
+                        options=Towards::iter().collect::<Vec<_>>()
+                        selected=&self.model.towards
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Amount"}
+                    error=get_error("amount")
+                >
+                    <input
+                        type="text"
+                        placeholder="0.00"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Amount(v.value)))
+                        value=&self.model.amount
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Date"}
+                    error=get_error("on")
+                >
+                    <input
+                        type="date"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::On(v.value)))
+                        value=&self.model.on
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Method"}
+                    error=get_error("method")
+                >
+                    <Select<Method>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Method(v)))
+                        options=Method::iter().collect::<Vec<_>>()
+                        selected=&self.model.method
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Reference"}
+                    error=get_error("reference")
+                >
+                    <input
+                        type="text"
+                        placeholder="Receipt or bank reference"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Reference(v.value)))
+                        value=&self.model.reference
+                    />
+                </form::Field>
+
+                <button
+                    type="submit"
+                    disabled={!self.errors.is_empty()}
+                >
+                    {"Record Payment"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl<V> Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    fn validate(&mut self) {
+        match self.props.validator.validate(&self.model) {
+            Err(errors) => self.errors = errors,
+            Ok(_) => self.errors.clear(),
+        };
+    }
+
+    // validate_edit ignores validation for fields not yet filled in.
+    fn validate_edit(&mut self) {
+        self.validate();
+
+        {
+            if self.model.tenant.name.is_empty() {
+                self.errors.remove("tenant");
+            }
+            if self.model.lease.is_none() {
+                self.errors.remove("lease");
+            }
+            if self.model.amount.is_empty() {
+                self.errors.remove("amount");
+            }
+            if self.model.on.is_empty() {
+                self.errors.remove("on");
+            }
+        }
+    }
+}
+
+impl Model {
+    /// payment parses the raw form input into a domain `Payment`, keyed by
+    /// field name on failure.
+    pub fn payment(&self) -> Result<Payment, HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        if self.lease.is_none() {
+            errors.insert("lease".into(), "must be selected".into());
+        }
+        let amount = self.amount.parse().ok();
+        if amount.is_none() {
+            errors.insert(
+                "amount".into(),
+                format!("must be an amount up to {}", Money::MAX),
+            );
+        }
+        let on = self.on.parse().ok();
+        if on.is_none() {
+            errors.insert("on".into(), "must be a date".into());
+        }
+
+        match (&self.lease, amount, on) {
+            (Some(lease), Some(amount), Some(on)) => Ok(Payment {
+                tenant: self.tenant.id,
+                lease: lease.lease.id,
+                amount,
+                on,
+                method: self.method,
+                reference: self.reference.trim().to_string(),
+                towards: self.towards,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl fmt::Display for LeaseChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "site {} from {} until {}",
+            &self.site_number,
+            self.lease.term.start,
+            self.lease.end()
+        )
+    }
+}
@@ -0,0 +1,231 @@
+//! Minimal PDF writer.
+//!
+//! Documents are built from positioned text and lines on A4 pages, using the
+//! standard Helvetica fonts so that nothing needs embedding. Everything here is
+//! plain Rust so it runs the same in the browser and in native tests.
+
+pub mod invoice;
+pub mod layout;
+
+use std::fmt::Write;
+
+/// Page size in points: A4 portrait.
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Document {
+    pub title: String,
+    pub pages: Vec<Page>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Page {
+    pub ops: Vec<Op>,
+}
+
+/// Op is one thing drawn on a page. Coordinates are in points from the
+/// bottom left corner.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Op {
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        font: Font,
+        text: String,
+    },
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Document {
+    /// to_bytes serialises the document as an uncompressed PDF 1.4 file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Objects 1 to 4 are the catalog, page tree, fonts; each page then
+        // takes two objects, the page and its content stream, and the info
+        // dictionary comes last.
+        let mut objects: Vec<String> = vec![];
+        let kids: Vec<String> = (0..self.pages.len())
+            .map(|ii| format!("{} 0 R", 5 + ii * 2))
+            .collect();
+        objects.push("<< /Type /Catalog /Pages 2 0 R >>".into());
+        objects.push(format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            self.pages.len()
+        ));
+        objects.push(Font::Regular.dictionary());
+        objects.push(Font::Bold.dictionary());
+        for (ii, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                num(PAGE_WIDTH),
+                num(PAGE_HEIGHT),
+                6 + ii * 2
+            ));
+            // Every character is written as a single byte.
+            let content = page.content();
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.chars().count(),
+                content
+            ));
+        }
+        objects.push(format!(
+            "<< /Title ({}) /Producer (Avisha) >>",
+            escape(&self.title)
+        ));
+        let info = objects.len();
+
+        let mut out: Vec<u8> = b"%PDF-1.4\n".to_vec();
+        let mut offsets = vec![];
+        for (ii, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n", ii + 1).bytes());
+            out.extend(latin1(object));
+            out.extend(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            info,
+            xref
+        );
+        out.extend(trailer.bytes());
+        out
+    }
+}
+
+impl Page {
+    // content is the page's content stream.
+    fn content(&self) -> String {
+        let mut content = String::new();
+        for op in &self.ops {
+            match op {
+                Op::Text {
+                    x,
+                    y,
+                    size,
+                    font,
+                    text,
+                } => {
+                    let _ = writeln!(
+                        content,
+                        "BT /{} {} Tf {} {} Td ({}) Tj ET",
+                        font.resource(),
+                        num(*size),
+                        num(*x),
+                        num(*y),
+                        escape(text)
+                    );
+                }
+                Op::Line { from, to, width } => {
+                    let _ = writeln!(
+                        content,
+                        "{} w {} {} m {} {} l S",
+                        num(*width),
+                        num(from.0),
+                        num(from.1),
+                        num(to.0),
+                        num(to.1)
+                    );
+                }
+            }
+        }
+        content.trim_end().to_string()
+    }
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+
+    fn dictionary(self) -> String {
+        let name = match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+        };
+        format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            name
+        )
+    }
+}
+
+/// text_width is how wide text is drawn at the given size, in points, using
+/// Helvetica's metrics. Bold text is slightly wider than this.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            code @ 32..=126 => HELVETICA_WIDTHS[(code - 32) as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+// HELVETICA_WIDTHS are the advance widths of printable ASCII in Helvetica,
+// in thousandths of the font size.
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+// num formats a coordinate with at most two decimal places.
+fn num(n: f32) -> String {
+    let s = format!("{:.2}", n);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// escape makes text safe inside a PDF string literal.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' | '\t' => escaped.push(' '),
+            c if (c as u32) < 256 => escaped.push(c),
+            // Outside WinAnsiEncoding.
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+// latin1 encodes text for the file; text has already been escaped, so every
+// character fits in a byte.
+fn latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
@@ -0,0 +1,351 @@
+//! Invoices and lease statements laid out for printing.
+
+use super::layout::{Align, Column, Layout, MARGIN};
+use super::{Document, Font, PAGE_WIDTH};
+use crate::domain::ledger::Account;
+use crate::domain::{Business, Invoice, LeaseId, Money, State, Towards};
+use chrono::NaiveDate as Date;
+
+const RIGHT: f32 = PAGE_WIDTH - MARGIN;
+
+/// invoice lays out an invoice as issued to the tenant.
+pub fn invoice(state: &State, invoice: &Invoice) -> Document {
+    let mut layout = Layout::new(&format!("Invoice #{}", invoice.number));
+    header(
+        &mut layout,
+        &state.business,
+        "INVOICE",
+        &[
+            format!("Invoice #{}", invoice.number),
+            format!("Issued {}", invoice.issued),
+            format!("Due {}", invoice.due),
+        ],
+    );
+    parties(state, &mut layout, invoice.lease);
+    layout.text(
+        &format!("Period: {} to {}", invoice.from, invoice.to),
+        10.0,
+        Font::Regular,
+    );
+    layout.space(16.0);
+
+    table_row(&mut layout, &["Description", "Amount"], Font::Bold);
+    layout.rule();
+    for line in &invoice.lines {
+        table_row(
+            &mut layout,
+            &[&line.description, &line.amount.to_string()],
+            Font::Regular,
+        );
+    }
+    layout.rule();
+
+    total(
+        &mut layout,
+        "Previous balance",
+        invoice.previous_balance,
+        Font::Regular,
+    );
+    total(&mut layout, "This invoice", invoice.total(), Font::Regular);
+    if !invoice.credit_applied.is_zero() {
+        total(
+            &mut layout,
+            "Credit applied",
+            -invoice.credit_applied,
+            Font::Regular,
+        );
+    }
+    total(&mut layout, "Amount due", invoice.amount_due(), Font::Bold);
+
+    payment(
+        &mut layout,
+        &state.business,
+        &format!(
+            "Please quote invoice #{} with your payment.",
+            invoice.number
+        ),
+    );
+    layout.finish()
+}
+
+/// statement lays out every charge and payment on a lease up to the given
+/// day, with the running balance.
+pub fn statement(state: &State, at: LeaseId, on: Date) -> Document {
+    let title = match state.lease(at) {
+        Some(lease) => format!(
+            "Statement for site {} from {}",
+            state.site_number(lease.site),
+            lease.term.start
+        ),
+        None => format!("Statement for {}", at),
+    };
+    let mut layout = Layout::new(&title);
+    header(
+        &mut layout,
+        &state.business,
+        "STATEMENT",
+        &[format!("As at {}", on)],
+    );
+    parties(state, &mut layout, at);
+    layout.space(16.0);
+
+    let columns = [MARGIN, MARGIN + 70.0, RIGHT - 160.0, RIGHT - 80.0, RIGHT];
+    let row = |layout: &mut Layout, cells: [&str; 5], font: Font| {
+        let columns: Vec<Column<'_>> = cells
+            .iter()
+            .zip(columns.iter())
+            .enumerate()
+            .map(|(ii, (text, x))| Column {
+                x: *x,
+                align: if ii < 2 { Align::Left } else { Align::Right },
+                text,
+            })
+            .collect();
+        layout.row(&columns, 9.0, font);
+    };
+
+    row(
+        &mut layout,
+        ["Date", "Entry", "Charges", "Payments", "Balance"],
+        Font::Bold,
+    );
+    layout.rule();
+    let receivables = [
+        Account::Receivable(at, Towards::Rent),
+        Account::Receivable(at, Towards::Utilities),
+    ];
+    let mut postings: Vec<_> = state
+        .ledger
+        .lease_postings(at)
+        .filter(|p| p.on <= on)
+        .filter(|p| receivables.contains(&p.debit) || receivables.contains(&p.credit))
+        .collect();
+    postings.sort_by_key(|p| p.on);
+    let mut balance = Money::ZERO;
+    for p in postings {
+        let (charge, paid) = if receivables.contains(&p.debit) {
+            balance = balance + p.amount;
+            (p.amount.to_string(), String::new())
+        } else {
+            balance = balance - p.amount;
+            (String::new(), p.amount.to_string())
+        };
+        row(
+            &mut layout,
+            [
+                &p.on.to_string(),
+                &p.source.to_string(),
+                &charge,
+                &paid,
+                &balance.to_string(),
+            ],
+            Font::Regular,
+        );
+    }
+    layout.rule();
+    total(&mut layout, "Balance owing", balance, Font::Bold);
+
+    payment(
+        &mut layout,
+        &state.business,
+        "Please quote your site number with your payment.",
+    );
+    layout.finish()
+}
+
+// header puts the business's details on the left and the document's title
+// and details on the right.
+fn header(layout: &mut Layout, business: &Business, title: &str, details: &[String]) {
+    let mut y = layout.y() - 18.0;
+    layout.at(RIGHT, y, title, 18.0, Font::Bold, Align::Right);
+    for detail in details {
+        y -= 14.0;
+        layout.at(RIGHT, y, detail, 10.0, Font::Regular, Align::Right);
+    }
+
+    layout.text(&business.name, 18.0, Font::Bold);
+    for line in business
+        .address
+        .lines()
+        .chain([business.phone.as_str(), business.email.as_str()])
+    {
+        if !line.trim().is_empty() {
+            layout.text(line.trim(), 10.0, Font::Regular);
+        }
+    }
+    // Clear the details if they run longer than the business's.
+    if layout.y() > y - 8.0 {
+        layout.space(layout.y() - y + 8.0);
+    }
+    layout.space(16.0);
+}
+
+// parties names the tenant and the site they lease.
+fn parties(state: &State, layout: &mut Layout, at: LeaseId) {
+    let lease = state.lease(at);
+    layout.text("Bill To", 11.0, Font::Bold);
+    if let Some(tenant) = lease.and_then(|l| state.tenant(l.tenant)) {
+        layout.text(&tenant.name, 10.0, Font::Regular);
+        let contact = &tenant.contact;
+        for line in contact.address.lines().filter(|l| !l.trim().is_empty()) {
+            layout.text(line.trim(), 10.0, Font::Regular);
+        }
+        if let Some(email) = contact.email() {
+            layout.text(email.as_str(), 10.0, Font::Regular);
+        }
+        if let Some(phone) = contact.phone() {
+            layout.text(phone.as_str(), 10.0, Font::Regular);
+        }
+    }
+    layout.space(8.0);
+    if let Some(site) = lease.and_then(|l| state.site(l.site)) {
+        layout.text(
+            &format!("Site {} ({})", &site.number, &site.kind),
+            10.0,
+            Font::Regular,
+        );
+    }
+    if let Some(lease) = lease {
+        layout.text(
+            &format!("Lease: {} to {}", lease.term.start, lease.end()),
+            10.0,
+            Font::Regular,
+        );
+    }
+}
+
+// table_row writes a description on the left and an amount on the right.
+fn table_row(layout: &mut Layout, cells: &[&str; 2], font: Font) {
+    layout.row(
+        &[
+            Column {
+                x: MARGIN,
+                align: Align::Left,
+                text: cells[0],
+            },
+            Column {
+                x: RIGHT,
+                align: Align::Right,
+                text: cells[1],
+            },
+        ],
+        10.0,
+        font,
+    );
+}
+
+// total writes a labelled amount aligned with the amounts column.
+fn total(layout: &mut Layout, label: &str, amount: Money, font: Font) {
+    layout.row(
+        &[
+            Column {
+                x: RIGHT - 110.0,
+                align: Align::Right,
+                text: label,
+            },
+            Column {
+                x: RIGHT,
+                align: Align::Right,
+                text: &amount.to_string(),
+            },
+        ],
+        10.0,
+        font,
+    );
+}
+
+// payment tells the tenant how to pay.
+fn payment(layout: &mut Layout, business: &Business, reference: &str) {
+    layout.space(20.0);
+    layout.text("How to Pay", 11.0, Font::Bold);
+    for line in business.payment_instructions.lines() {
+        layout.text(line.trim(), 10.0, Font::Regular);
+    }
+    layout.text(reference, 10.0, Font::Regular);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Meter, Method, Payment, Rate, Reading, Tariff, Utility};
+    use crate::testing::day;
+
+    fn fixture() -> (State, LeaseId) {
+        let (mut state, at) = crate::testing::fixture("jo@example.com");
+        let (tenant, site) = state.lease(at).map(|l| (l.tenant, l.site)).unwrap();
+        state
+            .add_meter(
+                site,
+                Meter {
+                    name: "Power".into(),
+                    utility: Utility::Electricity,
+                    readings: vec![],
+                },
+            )
+            .unwrap();
+        state
+            .add_tariff(Tariff {
+                utility: Utility::Electricity,
+                site: None,
+                supply: Money::from_cents(100),
+                rate: "0.30".parse::<Rate>().unwrap(),
+            })
+            .unwrap();
+        for (on, value) in [(day(1, 1), 1000), (day(1, 7), 1060)] {
+            state
+                .record_reading(site, "Power", Reading { on, value })
+                .unwrap();
+        }
+        state.issue_invoice(at, day(1, 1)).unwrap();
+        state
+            .record_payment(Payment {
+                tenant,
+                lease: at,
+                amount: Money::from_units(200),
+                on: day(1, 3),
+                method: Method::BankTransfer,
+                reference: "JC7".into(),
+                towards: Towards::Rent,
+            })
+            .unwrap();
+        state.issue_invoice(at, day(1, 8)).unwrap();
+        (state, at)
+    }
+
+    #[test]
+    fn invoice_layout() {
+        let (state, _) = fixture();
+        insta::assert_debug_snapshot!(invoice(&state, &state.invoices[1]));
+    }
+
+    #[test]
+    fn invoice_file() {
+        let (state, _) = fixture();
+        let bytes = invoice(&state, &state.invoices[0]).to_bytes();
+        insta::assert_snapshot!(String::from_utf8_lossy(&bytes));
+    }
+
+    #[test]
+    fn statement_layout() {
+        let (state, at) = fixture();
+        insta::assert_debug_snapshot!(statement(&state, at, day(1, 10)));
+    }
+
+    #[test]
+    fn long_statement_breaks_pages() {
+        let (mut state, at) = fixture();
+        for ii in 0..60 {
+            state
+                .write_off(
+                    at,
+                    Towards::Rent,
+                    Money::from_cents(1),
+                    format!("rounding {}", ii),
+                    day(1, 9),
+                )
+                .unwrap();
+        }
+        let document = statement(&state, at, day(1, 10));
+        assert_eq!(document.pages.len(), 2);
+    }
+}
@@ -0,0 +1,129 @@
+//! Layout flows content down the page, starting a new page when one fills.
+
+use super::{text_width, Document, Font, Op, Page, PAGE_HEIGHT, PAGE_WIDTH};
+
+pub const MARGIN: f32 = 50.0;
+
+/// Layout tracks where the next line of content goes.
+pub struct Layout {
+    document: Document,
+    /// Baseline of the current line, in points from the bottom of the page.
+    y: f32,
+}
+
+/// Align is which edge of a column text is aligned to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// Column is a span of text on a row, positioned by one edge.
+pub struct Column<'a> {
+    pub x: f32,
+    pub align: Align,
+    pub text: &'a str,
+}
+
+impl Layout {
+    pub fn new(title: &str) -> Self {
+        Layout {
+            document: Document {
+                title: title.to_string(),
+                pages: vec![Page::default()],
+            },
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// text writes a line of text at the left margin and moves down past it.
+    pub fn text(&mut self, text: &str, size: f32, font: Font) {
+        self.row(
+            &[Column {
+                x: MARGIN,
+                align: Align::Left,
+                text,
+            }],
+            size,
+            font,
+        );
+    }
+
+    /// row writes text in columns on one line and moves down past it.
+    pub fn row(&mut self, columns: &[Column<'_>], size: f32, font: Font) {
+        let height = size * 1.4;
+        self.ensure(height);
+        self.y -= size;
+        for column in columns.iter().filter(|c| !c.text.is_empty()) {
+            let x = match column.align {
+                Align::Left => column.x,
+                Align::Right => column.x - text_width(column.text, size),
+            };
+            self.draw(Op::Text {
+                x,
+                y: self.y,
+                size,
+                font,
+                text: column.text.to_string(),
+            });
+        }
+        self.y -= height - size;
+    }
+
+    /// at writes text at a fixed position on the current page, without
+    /// moving down.
+    pub fn at(&mut self, x: f32, y: f32, text: &str, size: f32, font: Font, align: Align) {
+        let x = match align {
+            Align::Left => x,
+            Align::Right => x - text_width(text, size),
+        };
+        self.draw(Op::Text {
+            x,
+            y,
+            size,
+            font,
+            text: text.to_string(),
+        });
+    }
+
+    /// rule draws a horizontal line across the page between the margins.
+    pub fn rule(&mut self) {
+        self.ensure(6.0);
+        self.y -= 3.0;
+        self.draw(Op::Line {
+            from: (MARGIN, self.y),
+            to: (PAGE_WIDTH - MARGIN, self.y),
+            width: 0.5,
+        });
+        self.y -= 3.0;
+    }
+
+    /// space moves down, starting a new page if this one is full.
+    pub fn space(&mut self, height: f32) {
+        self.ensure(height);
+        self.y -= height;
+    }
+
+    /// y is the baseline of the next line of content.
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    pub fn finish(self) -> Document {
+        self.document
+    }
+
+    // ensure starts a new page unless `height` fits above the bottom margin.
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.document.pages.push(Page::default());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn draw(&mut self, op: Op) {
+        if let Some(page) = self.document.pages.last_mut() {
+            page.ops.push(op);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::domain::{Meter, Reading, Site, State as AppState};
+use crate::form;
+use crate::validate::Validate;
+
+use std::collections::HashMap;
+use std::mem;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yew_components::Select;
+use yewtil::NeqAssign;
+
+pub struct Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    pub link: ComponentLink<Self>,
+    pub props: Props<V>,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Model {
+    pub site: Site,
+    pub meter: Meter,
+    pub on: String,
+    pub value: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props<V>
+where
+    V: Validate + Clone,
+{
+    pub submit: Callback<Model>,
+    pub state: AppState,
+    pub validator: V,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    Site(Site),
+    Meter(Meter),
+    On(String),
+    Value(String),
+}
+
+impl<V> Component for Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    type Message = Msg;
+    type Properties = Props<V>;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::Site(v) => {
+                        self.model.site = v;
+                        self.model.meter = Meter::default();
+                    }
+                    Field::Meter(v) => self.model.meter = v,
+                    Field::On(v) => self.model.on = v,
+                    Field::Value(v) => self.model.value = v,
+                };
+                self.validate_edit();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.props.validator.validate(&self.model).is_ok() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        let sites = self
+            .props
+            .state
+            .sites
+            .values()
+            .filter(|s| !s.meters.is_empty())
+            .cloned()
+            .collect::<Vec<Site>>();
+
+        // Offer the meters of the selected site as currently stored, so the
+        // latest reading is shown beside the input.
+        let site = self.props.state.site(self.model.site.id);
+        let meters = site.map(|s| s.meters.clone()).unwrap_or_default();
+        let last = site
+            .and_then(|s| s.meter(&self.model.meter.name))
+            .and_then(|m| m.readings.last());
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Site"}
+                    error=get_error("site")
+                >
+                    <Select<Site>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Site(v)))
+                        options=sites
+                        selected=&self.model.site
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Meter"}
+                    error=get_error("meter")
+                >
+                    <Select<Meter>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Meter(v)))
+                        options=meters
+                        selected=&self.model.meter
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Date"}
+                    error=get_error("on")
+                >
+                    <input
+                        type="date"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::On(v.value)))
+                        value=&self.model.on
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={match last {
+                        Some(r) => format!("Reading (last {} on {})", r.value, r.on),
+                        None => "Reading".to_string(),
+                    }}
+                    error=get_error("value")
+                >
+                    <input
+                        type="number"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Value(v.value)))
+                        value=&self.model.value
+                    />
+                </form::Field>
+
+                <button
+                    type="submit"
+                    disabled={!self.errors.is_empty()}
+                >
+                    {"Record"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl<V> Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    fn validate(&mut self) {
+        match self.props.validator.validate(&self.model) {
+            Err(errors) => self.errors = errors,
+            Ok(_) => self.errors.clear(),
+        };
+    }
+
+    // validate_edit ignores validation for fields not yet filled in.
+    fn validate_edit(&mut self) {
+        self.validate();
+
+        {
+            if self.model.site.number.is_empty() {
+                self.errors.remove("site");
+            }
+            if self.model.meter.name.is_empty() {
+                self.errors.remove("meter");
+            }
+            if self.model.on.is_empty() {
+                self.errors.remove("on");
+            }
+            if self.model.value.is_empty() {
+                self.errors.remove("value");
+            }
+        }
+    }
+}
+
+impl Model {
+    /// reading parses the raw form input into a domain `Reading`, keyed by
+    /// field name on failure.
+    pub fn reading(&self) -> Result<Reading, HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        let on = self.on.parse().ok();
+        if on.is_none() {
+            errors.insert("on".into(), "must be a date".into());
+        }
+        let value = self.value.trim().parse().ok();
+        if value.is_none() {
+            errors.insert("value".into(), "must be a whole number".into());
+        }
+
+        match (on, value) {
+            (Some(on), Some(value)) => Ok(Reading { on, value }),
+            _ => Err(errors),
+        }
+    }
+}
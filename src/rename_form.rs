@@ -0,0 +1,110 @@
+use crate::form;
+use std::collections::HashMap;
+use std::mem;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+/// Form collects a new name for a tenant, or a new number for a site.
+pub struct Form {
+    pub link: ComponentLink<Self>,
+    pub props: Props,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Model {
+    pub name: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub label: String,
+    pub action: String,
+    /// Names already in use, which cannot be taken.
+    pub taken: Vec<String>,
+    pub submit: Callback<Model>,
+}
+
+pub enum Msg {
+    Edit(String),
+    Submit,
+}
+
+impl Component for Form {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(v) => {
+                self.model.name = v;
+                self.errors.clear();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.errors.is_empty() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label=&self.props.label
+                    error=get_error("name")
+                >
+                    <input
+                        type="text"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(v.value))
+                        value=&self.model.name
+                    />
+                </form::Field>
+
+                <button type="submit">
+                    {&self.props.action}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl Form {
+    fn validate(&mut self) {
+        self.errors.clear();
+
+        if self.model.name.is_empty() {
+            self.errors.insert("name".into(), "must be non-zero".into());
+        } else if self.props.taken.contains(&self.model.name) {
+            self.errors.insert("name".into(), "must be unique".into());
+        }
+    }
+}
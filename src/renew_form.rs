@@ -0,0 +1,154 @@
+use crate::domain::{Days, Money, MAX_TERM};
+use crate::form;
+use std::collections::HashMap;
+use std::mem;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+/// Form collects the length of a follow-on term and, optionally, a new rent
+/// for renewing a lease.
+pub struct Form {
+    pub link: ComponentLink<Self>,
+    pub props: Props,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Model {
+    pub duration: String,
+    pub rent: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub submit: Callback<Model>,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    Duration(String),
+    Rent(String),
+}
+
+impl Component for Form {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::Duration(v) => self.model.duration = v,
+                    Field::Rent(v) => self.model.rent = v,
+                };
+                self.errors.clear();
+            }
+            Msg::Submit => {
+                if let Err(errors) = self.model.parse() {
+                    self.errors = errors;
+                } else {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Duration (days)"}
+                    error=get_error("duration")
+                >
+                    <input
+                        type="number"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Duration(v.value)))
+                        value=&self.model.duration
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"New Rent"}
+                    error=get_error("rent")
+                >
+                    <input
+                        type="text"
+                        placeholder="Unchanged"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Rent(v.value)))
+                        value=&self.model.rent
+                    />
+                </form::Field>
+
+                <button type="submit">
+                    {"Renew"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl Model {
+    /// parse yields the duration and, if one was entered, the new rent.
+    pub fn parse(&self) -> Result<(Days, Option<Money>), HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        let duration = self
+            .duration
+            .parse()
+            .ok()
+            .filter(|d| (1..=MAX_TERM).contains(d));
+        if duration.is_none() {
+            errors.insert(
+                "duration".into(),
+                format!("must be a number of days up to {}", MAX_TERM),
+            );
+        }
+
+        let rent = if self.rent.trim().is_empty() {
+            Some(None)
+        } else {
+            self.rent.parse().ok().map(Some)
+        };
+        if rent.is_none() {
+            errors.insert(
+                "rent".into(),
+                format!("must be an amount up to {}", Money::MAX),
+            );
+        }
+
+        match (duration, rent) {
+            (Some(duration), Some(rent)) => Ok((duration, rent)),
+            _ => Err(errors),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::domain::SiteKind;
 use crate::form;
 use crate::validate::Validate;
 use std::collections::HashMap;
@@ -93,10 +94,10 @@ where
                 if self.props.validator.validate(&self.model).is_ok() {
                     self.props
                         .submit
-                        .emit(mem::replace(&mut self.model, Model::default()));
+                        .emit(mem::take(&mut self.model));
                 }
             }
-            Msg::Nope | _ => {}
+            Msg::Nope => {}
         };
 
         true
@@ -203,7 +204,7 @@ impl From<&str> for Kind {
             "cabin" => Kind::Cabin,
             "house" => Kind::House,
             "flat" => Kind::Flat,
-            s if s.is_empty() => Kind::Cabin,
+            "" => Kind::Cabin,
             _ => Kind::Other(s),
         }
     }
@@ -216,4 +217,15 @@ impl Default for Model {
             kind: Kind::Cabin,
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<Kind> for SiteKind {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Cabin => SiteKind::Cabin,
+            Kind::House => SiteKind::House,
+            Kind::Flat => SiteKind::Flat,
+            Kind::Other(v) => SiteKind::Other(v),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! SMS reminders for rent falling due and money overdue.
+//!
+//! Like mail, SMS is sent from the command line (see `src/bin/avisha-sms.rs`)
+//! against state exported from the app. Gateways are pluggable; the HTTP
+//! gateway talks to a provider's API, and `mock` serves a stand-in for it on
+//! the local machine.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod http;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mock;
+
+use crate::domain::{Channel, DeliveryStatus, Notification, Reminder, State, TenantId};
+use chrono::{Duration, NaiveDate as Date};
+use std::fmt;
+
+/// RENT_DUE_NOTICE is how many days ahead tenants are reminded of rent.
+pub const RENT_DUE_NOTICE: i64 = 2;
+
+/// OVERDUE_INTERVAL is how many days apart overdue reminders are sent.
+pub const OVERDUE_INTERVAL: i64 = 7;
+
+pub const RENT_DUE_TEMPLATE: &str =
+    "Hi {tenant}, rent of {amount} for site {site} is due on {due}. Thank you, {business}";
+
+pub const OVERDUE_TEMPLATE: &str = "Hi {tenant}, {amount} has been owing on site {site} \
+     since {since}. Please pay as soon as you can or get in touch. {business}";
+
+/// Gateway sends text messages through an SMS provider.
+pub trait Gateway {
+    /// send delivers `body` to the phone number `to`, returning the
+    /// provider's id for the message.
+    fn send(&mut self, to: &str, body: &str) -> Result<String, Error>;
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// NoPhone names a tenant with no phone number.
+    NoPhone(String),
+    Gateway(String),
+}
+
+/// Memory keeps sent messages, for tests.
+#[derive(Default, Debug)]
+pub struct Memory {
+    pub sent: Vec<(String, String)>,
+}
+
+impl Gateway for Memory {
+    fn send(&mut self, to: &str, body: &str) -> Result<String, Error> {
+        self.sent.push((to.to_string(), body.to_string()));
+        Ok(self.sent.len().to_string())
+    }
+}
+
+/// reminders_due lists the reminders to send on the given day: rent falling
+/// due within the notice period on running leases, and money overdue on any
+/// lease. Reminders already sent, and those to tenants who prefer another
+/// channel, are left out.
+pub fn reminders_due(state: &State, on: Date) -> Vec<(TenantId, Reminder, String)> {
+    let mut due = vec![];
+    for lease in state.leases().filter(|l| l.is_active(on)) {
+        let business = &state.business.name;
+        for charge in lease.rent_schedule().iter().filter(|c| {
+            c.due >= on && c.due <= on + Duration::days(RENT_DUE_NOTICE) && !c.amount.is_zero()
+        }) {
+            let reminder = Reminder::RentDue {
+                lease: lease.id,
+                due: charge.due,
+            };
+            let body = render(
+                RENT_DUE_TEMPLATE,
+                &[
+                    ("tenant", state.tenant_name(lease.tenant)),
+                    ("amount", &charge.amount.to_string()),
+                    ("site", state.site_number(lease.site)),
+                    ("due", &charge.due.to_string()),
+                    ("business", business),
+                ],
+            );
+            due.push((lease.tenant, reminder, body));
+        }
+    }
+    for arrears in state.arrears(on).into_iter().filter(|a| a.days(on) > 0) {
+        let reminder = Reminder::Overdue {
+            lease: arrears.lease,
+            since: arrears.since,
+        };
+        let body = render(
+            OVERDUE_TEMPLATE,
+            &[
+                ("tenant", &arrears.tenant_name),
+                ("amount", &arrears.total().to_string()),
+                ("site", &arrears.site_number),
+                ("since", &arrears.since.to_string()),
+                ("business", &state.business.name),
+            ],
+        );
+        due.push((arrears.tenant, reminder, body));
+    }
+    due.retain(|(tenant, reminder, _)| {
+        state
+            .tenant(*tenant)
+            .is_some_and(|t| t.contact.preferred == Channel::Sms)
+            && !already_sent(state, reminder, on)
+    });
+    due
+}
+
+/// send_reminders sends every reminder due and logs each against its tenant,
+/// whether or not it could be sent.
+pub fn send_reminders(state: &mut State, gateway: &mut dyn Gateway, on: Date) -> Vec<Notification> {
+    let mut sent = vec![];
+    for (tenant, reminder, body) in reminders_due(state, on) {
+        let phone = state
+            .tenant(tenant)
+            .and_then(|t| t.contact.phone())
+            .map(|p| p.to_string());
+        let (to, result) = match phone {
+            Some(to) => {
+                let result = gateway.send(&to, &body);
+                (to, result)
+            }
+            None => (
+                String::new(),
+                Err(Error::NoPhone(state.tenant_name(tenant).to_string())),
+            ),
+        };
+        let notification = Notification {
+            tenant,
+            on,
+            channel: Channel::Sms,
+            to,
+            reminder,
+            body,
+            status: match result {
+                Ok(_) => DeliveryStatus::Sent,
+                Err(err) => DeliveryStatus::Failed(err.to_string()),
+            },
+        };
+        // The tenant comes from a lease, so is registered.
+        let _ = state.log_notification(notification.clone());
+        sent.push(notification);
+    }
+    sent
+}
+
+/// render fills `{name}` placeholders in a template.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+// already_sent reports whether a reminder has gone out: rent due reminders
+// once per charge, and overdue reminders once per interval.
+fn already_sent(state: &State, reminder: &Reminder, on: Date) -> bool {
+    state
+        .notifications
+        .iter()
+        .filter(|n| n.status == DeliveryStatus::Sent)
+        .any(|n| match (&n.reminder, reminder) {
+            (Reminder::RentDue { .. }, Reminder::RentDue { .. }) => &n.reminder == reminder,
+            (Reminder::Overdue { lease, .. }, Reminder::Overdue { .. }) => {
+                *lease == reminder.lease() && n.on > on - Duration::days(OVERDUE_INTERVAL)
+            }
+            _ => false,
+        })
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoPhone(name) => write!(f, "tenant {:?} has no phone number", name),
+            Error::Gateway(reason) => write!(f, "sending failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{day, fixture};
+
+    #[test]
+    fn reminds_of_rent_due_once() {
+        let (mut state, _) = fixture("0400 123 456");
+        let mut gateway = Memory::default();
+
+        send_reminders(&mut state, &mut gateway, day(1, 6));
+        send_reminders(&mut state, &mut gateway, day(1, 7));
+        assert_eq!(
+            gateway.sent,
+            vec![(
+                "+61400123456".to_string(),
+                "Hi Jo Citizen, rent of $250.00 for site 7 is due on 2024-01-08. \
+                 Thank you, Avisha Holiday Park"
+                    .to_string()
+            )]
+        );
+        let tenant = state.tenant_named("Jo Citizen").unwrap().id;
+        assert_eq!(state.notifications_for(tenant).count(), 1);
+    }
+
+    #[test]
+    fn reminds_of_overdue_rent_weekly() {
+        let (mut state, _) = fixture("+61 400 123 456");
+        state.post_rent_due(day(1, 3)).unwrap();
+        let mut gateway = Memory::default();
+
+        for on in 3..=11 {
+            state.post_rent_due(day(1, on)).unwrap();
+            send_reminders(&mut state, &mut gateway, day(1, on));
+        }
+        let overdue: Vec<_> = state
+            .notifications
+            .iter()
+            .filter(|n| matches!(n.reminder, Reminder::Overdue { .. }))
+            .map(|n| n.on)
+            .collect();
+        assert_eq!(overdue, vec![day(1, 3), day(1, 10)]);
+        assert!(gateway.sent.iter().all(|(to, _)| to == "+61400123456"));
+    }
+
+    #[test]
+    fn logs_tenants_without_a_phone() {
+        let (mut state, _) = fixture("jo@example.com");
+        for tenant in state.tenants.values_mut() {
+            tenant.contact.preferred = Channel::Sms;
+        }
+        let mut gateway = Memory::default();
+
+        let sent = send_reminders(&mut state, &mut gateway, day(1, 6));
+        assert!(gateway.sent.is_empty());
+        assert_eq!(
+            sent[0].status,
+            DeliveryStatus::Failed(Error::NoPhone("Jo Citizen".into()).to_string())
+        );
+    }
+
+    #[test]
+    fn texts_only_tenants_who_prefer_it() {
+        let (mut state, _) = fixture("jo@example.com, 0400 123 456");
+        let mut gateway = Memory::default();
+
+        assert!(reminders_due(&state, day(1, 6)).is_empty());
+        let sent = send_reminders(&mut state, &mut gateway, day(1, 6));
+        assert!(sent.is_empty());
+        assert!(gateway.sent.is_empty());
+    }
+}
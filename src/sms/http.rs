@@ -0,0 +1,94 @@
+//! Delivery through an SMS provider's HTTP API.
+
+use super::{Error, Gateway};
+use serde_derive::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Http posts each message as JSON, `{"to": ..., "body": ...}`, to an
+/// endpoint such as `http://127.0.0.1:8025/messages`, which answers with
+/// `{"id": ...}`. Only plain HTTP is spoken, so a provider on the internet
+/// is reached through a local relay.
+pub struct Http {
+    host: String,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    to: &'a str,
+    body: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: String,
+}
+
+impl Http {
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| Error::Gateway(format!("{:?} is not an http:// URL", url)))?;
+        let (host, path) = match rest.find('/') {
+            Some(ii) => (&rest[..ii], &rest[ii..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(Error::Gateway(format!("{:?} has no host", url)));
+        }
+        let host = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        Ok(Http {
+            host,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Gateway for Http {
+    fn send(&mut self, to: &str, body: &str) -> Result<String, Error> {
+        let gateway = |err: std::io::Error| Error::Gateway(err.to_string());
+        let payload = serde_json::to_string(&Request { to, body })
+            .map_err(|err| Error::Gateway(err.to_string()))?;
+
+        let mut stream = TcpStream::connect(&self.host).map_err(gateway)?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(gateway)?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            &self.path,
+            &self.host,
+            payload.len(),
+            payload
+        )
+        .map_err(gateway)?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(gateway)?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| Error::Gateway("malformed response".into()))?;
+        let status = head.lines().next().unwrap_or_default();
+        if !status
+            .split(' ')
+            .nth(1)
+            .is_some_and(|code| code.starts_with('2'))
+        {
+            return Err(Error::Gateway(status.to_string()));
+        }
+        serde_json::from_str::<Response>(body)
+            .map(|r| r.id)
+            .map_err(|err| Error::Gateway(err.to_string()))
+    }
+}
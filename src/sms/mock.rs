@@ -0,0 +1,95 @@
+//! A stand-in for an SMS provider, serving the API the HTTP gateway speaks
+//! on the local machine and printing messages instead of sending them.
+
+use serde_derive::Deserialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+#[derive(Deserialize, Debug)]
+pub struct Message {
+    pub to: String,
+    pub body: String,
+}
+
+/// serve answers requests on `listener` until it fails, passing each message
+/// received to `received`.
+pub fn serve(listener: TcpListener, mut received: impl FnMut(&Message)) -> io::Result<()> {
+    let mut count = 0;
+    for stream in listener.incoming() {
+        count += 1;
+        if let Some(message) = handle(stream?, count)? {
+            received(&message);
+        }
+    }
+    Ok(())
+}
+
+// handle reads one request and answers it, accepting the message as
+// `mock-<count>` or refusing it if it can't be read.
+fn handle(stream: TcpStream, count: usize) -> io::Result<Option<Message>> {
+    let mut reader = BufReader::new(stream);
+    let mut length = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let message = serde_json::from_slice::<Message>(&body).ok();
+    let (status, reply) = match &message {
+        Some(_) => ("200 OK", format!("{{\"id\":\"mock-{}\"}}", count)),
+        None => ("400 Bad Request", "{\"error\":\"bad message\"}".to_string()),
+    };
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reply.len(),
+        reply
+    )?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sms::{http::Http, Gateway};
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn http_gateway_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/messages", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            serve(listener, |m| {
+                tx.send((m.to.clone(), m.body.clone())).unwrap();
+            })
+        });
+
+        let mut gateway = Http::new(&url).unwrap();
+        assert_eq!(gateway.send("+61400123456", "Hi \"Jo\"").unwrap(), "mock-1");
+        assert_eq!(gateway.send("0400123456", "again").unwrap(), "mock-2");
+        assert_eq!(
+            rx.recv().unwrap(),
+            ("+61400123456".to_string(), "Hi \"Jo\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,623 @@
+//! Saving state and reading it back.
+//!
+//! State is saved in an envelope recording the version of its shape, so that
+//! state saved by an earlier version of the app can be migrated forward
+//! before it is read. State saved before envelopes were used is version 0.
+//!
+//! Each change is saved as an event appended to a log, and a snapshot of the
+//! whole state is saved every `SNAPSHOT_EVERY` events. State is loaded by
+//! replaying the events logged since the last snapshot onto it.
+//!
+//! Where it is saved is up to a `Store`: local storage, IndexedDB for more
+//! than local storage can hold, or memory for tests.
+
+pub mod indexed_db;
+pub mod local;
+mod migrate;
+
+pub use migrate::VERSION;
+
+use crate::domain::{audit, Audited, Change, Error as DomainError, Event, State};
+use chrono::Utc;
+use indexed_db::IndexedDb;
+use local::Local;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// SNAPSHOT_EVERY is how many events are logged between snapshots.
+pub const SNAPSHOT_EVERY: usize = 100;
+
+/// Store keeps state between sessions. Backends hold a snapshot and a log of
+/// events, and loading, saving and recording are built on them.
+pub trait Store {
+    /// read returns the snapshot saved last, if one has been saved.
+    fn read(&mut self) -> Result<Option<String>, Error>;
+
+    /// write replaces the snapshot.
+    fn write(&mut self, data: String) -> Result<(), Error>;
+
+    /// read_log returns the events logged, oldest first, skipping the first
+    /// `from`.
+    fn read_log(&mut self, from: usize) -> Result<Vec<String>, Error>;
+
+    /// append adds an event to the end of the log.
+    fn append(&mut self, event: String) -> Result<(), Error>;
+
+    /// logged is how many events have been logged.
+    fn logged(&mut self) -> Result<usize, Error>;
+
+    /// quarantine keeps data that could not be read where writes will not
+    /// overwrite it, and says where that is.
+    fn quarantine(&mut self, data: String) -> Result<String, Error>;
+
+    /// load reads the snapshot saved last, migrating it from whichever
+    /// version saved it, and replays the events logged since.
+    ///
+    /// A snapshot that cannot be read is quarantined and replaced by empty
+    /// state. Events that cannot be replayed are quarantined along with those
+    /// after them, and replaced by a snapshot of the state replayed before
+    /// them. Either way what is saved in their place can be loaded, and
+    /// events logged from then on are replayed onto it.
+    fn load(&mut self) -> Result<Option<State>, Error> {
+        let (covered, mut state) = match self.read()? {
+            Some(data) => match read_snapshot(&data) {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    let at = self.quarantine(data)?;
+                    self.save(&State::default())?;
+                    return Err(Error::Quarantined {
+                        at,
+                        reason: err.to_string(),
+                    });
+                }
+            },
+            None if self.logged()? == 0 => return Ok(None),
+            None => (0, State::default()),
+        };
+        let log = self.read_log(covered)?;
+        for (ii, data) in log.iter().enumerate() {
+            let replayed = match read_event(data) {
+                Ok(event) => state.apply(event).map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(reason) = replayed {
+                let reason = Error::Replay {
+                    at: covered + ii + 1,
+                    reason,
+                };
+                let rest = serde_json::to_string(&log[ii..])
+                    .map_err(|err| Error::Encoding(err.to_string()))?;
+                let at = self.quarantine(rest)?;
+                self.save(&state)?;
+                return Err(Error::Quarantined {
+                    at,
+                    reason: reason.to_string(),
+                });
+            }
+        }
+        Ok(Some(state))
+    }
+
+    /// save replaces the saved state with a snapshot of `state`, so that
+    /// the events logged so far are no longer replayed onto it.
+    fn save(&mut self, state: &State) -> Result<(), Error> {
+        let covered = self.logged()?;
+        let data = write_snapshot(state, covered)?;
+        self.write(data)
+    }
+
+    /// record applies an event to `state` and logs it, along with when it was
+    /// made, by whom, and the records it changed, taking a snapshot when one
+    /// is due. It returns the changes logged. If the event fails or cannot be
+    /// logged, `state` is left as it was; an event that changes nothing is not
+    /// logged.
+    ///
+    /// Only the records in the event's scope, and those it adds, are copied
+    /// and compared.
+    fn record(
+        &mut self,
+        state: &mut State,
+        event: Event,
+        actor: &str,
+    ) -> Result<Vec<Change>, Error> {
+        let kept = state.keep(&event.scope());
+        let applied = state.apply(event.clone());
+        let changes = state.changes_since(&kept);
+        if let Err(err) = applied {
+            state.restore(audit::reverse(changes));
+            return Err(Error::Domain(err));
+        }
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        let audited = Audited {
+            at: Some(Utc::now()),
+            actor: actor.to_string(),
+            description: event.describe(state),
+            changes,
+        };
+        let logged = serde_json::to_string(&Entry {
+            version: VERSION,
+            event: &event,
+            audited: &audited,
+        })
+        .map_err(|err| Error::Encoding(err.to_string()))
+        .and_then(|data| self.append(data));
+        if let Err(err) = logged {
+            state.restore(audit::reverse(audited.changes));
+            return Err(err);
+        }
+        if self.logged()? % SNAPSHOT_EVERY == 0 {
+            self.save(state)?;
+        }
+        Ok(audited.changes)
+    }
+
+    /// audit reads the audit trail: every change logged, oldest first.
+    fn audit(&mut self) -> Result<Vec<Audited>, Error> {
+        self.read_log(0)?
+            .iter()
+            .map(|data| read_entry(data).map(|(_, audited)| audited))
+            .collect()
+    }
+}
+
+impl<S: Store + ?Sized> Store for Box<S> {
+    fn read(&mut self) -> Result<Option<String>, Error> {
+        (**self).read()
+    }
+
+    fn write(&mut self, data: String) -> Result<(), Error> {
+        (**self).write(data)
+    }
+
+    fn read_log(&mut self, from: usize) -> Result<Vec<String>, Error> {
+        (**self).read_log(from)
+    }
+
+    fn append(&mut self, event: String) -> Result<(), Error> {
+        (**self).append(event)
+    }
+
+    fn logged(&mut self) -> Result<usize, Error> {
+        (**self).logged()
+    }
+
+    fn quarantine(&mut self, data: String) -> Result<String, Error> {
+        (**self).quarantine(data)
+    }
+}
+
+/// Memory keeps saved data for as long as the app runs, for tests and for
+/// when nothing else is available.
+#[derive(Default, Debug)]
+pub struct Memory {
+    pub saved: Option<String>,
+    pub log: Vec<String>,
+    pub quarantined: Vec<String>,
+}
+
+impl Store for Memory {
+    fn read(&mut self) -> Result<Option<String>, Error> {
+        Ok(self.saved.clone())
+    }
+
+    fn write(&mut self, data: String) -> Result<(), Error> {
+        self.saved = Some(data);
+        Ok(())
+    }
+
+    fn read_log(&mut self, from: usize) -> Result<Vec<String>, Error> {
+        Ok(self.log.iter().skip(from).cloned().collect())
+    }
+
+    fn append(&mut self, event: String) -> Result<(), Error> {
+        self.log.push(event);
+        Ok(())
+    }
+
+    fn logged(&mut self) -> Result<usize, Error> {
+        Ok(self.log.len())
+    }
+
+    fn quarantine(&mut self, data: String) -> Result<String, Error> {
+        self.quarantined.push(data);
+        Ok(format!("memory, as quarantine #{}", self.quarantined.len()))
+    }
+}
+
+/// open opens the store of the given name: "local" for local storage,
+/// "indexeddb" for IndexedDB, or "memory" to keep nothing between sessions.
+pub async fn open(name: &str) -> Result<Box<dyn Store>, Error> {
+    match name {
+        "local" => Ok(Box::new(Local::new()?)),
+        "indexeddb" => Ok(Box::new(IndexedDb::open().await?)),
+        "memory" => Ok(Box::new(Memory::default())),
+        _ => Err(Error::Unknown(name.to_string())),
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    /// How many logged events a snapshot takes in. Exported state, which
+    /// has no log, leaves it out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    events: Option<usize>,
+    state: &'a State,
+}
+
+// Entry is an event in the log, with the version of its shape and its place
+// in the audit trail.
+#[derive(Serialize)]
+struct Entry<'a> {
+    version: u32,
+    event: &'a Event,
+    #[serde(flatten)]
+    audited: &'a Audited,
+}
+
+#[derive(Deserialize)]
+struct StoredEntry {
+    version: u32,
+    event: Value,
+    #[serde(flatten)]
+    audited: Audited,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Malformed is data that is not JSON.
+    Malformed(String),
+    /// Newer carries the version of state saved by a later version of the
+    /// app, which this one cannot read.
+    Newer(u32),
+    /// Invalid is state that does not have the shape of its version.
+    Invalid { version: u32, reason: String },
+    /// Quarantined is saved data that could not be read, and says where it
+    /// has been kept.
+    Quarantined { at: String, reason: String },
+    /// Encoding is state that could not be written out as JSON.
+    Encoding(String),
+    /// Storage is a failure of the place state is kept, such as it being
+    /// unavailable or full.
+    Storage(String),
+    /// Unknown names a store that does not exist.
+    Unknown(String),
+    /// Replay is an event that could not be replayed, numbered from the
+    /// start of the log.
+    Replay { at: usize, reason: String },
+    /// Domain is an event refused because it would leave state inconsistent.
+    Domain(DomainError),
+}
+
+/// to_string saves state in an envelope of the current version.
+pub fn to_string(state: &State) -> serde_json::Result<String> {
+    serde_json::to_string(&Envelope {
+        version: VERSION,
+        events: None,
+        state,
+    })
+}
+
+/// from_str reads saved state of any version, migrating it to the current
+/// shape.
+pub fn from_str(data: &str) -> Result<State, Error> {
+    read_snapshot(data).map(|(_, state)| state)
+}
+
+// write_snapshot saves state along with how many logged events it takes in.
+fn write_snapshot(state: &State, events: usize) -> Result<String, Error> {
+    serde_json::to_string(&Envelope {
+        version: VERSION,
+        events: Some(events),
+        state,
+    })
+    .map_err(|err| Error::Encoding(err.to_string()))
+}
+
+// read_snapshot reads saved state and how many logged events it takes in,
+// which is none for state saved before the log.
+fn read_snapshot(data: &str) -> Result<(usize, State), Error> {
+    let value: Value =
+        serde_json::from_str(data).map_err(|err| Error::Malformed(err.to_string()))?;
+    let events = value.get("events").and_then(Value::as_u64).unwrap_or(0);
+    let (version, mut state) = unwrap(value);
+    if version > VERSION {
+        return Err(Error::Newer(version));
+    }
+    migrate::upgrade(&mut state, version);
+    let state = serde_json::from_value(state).map_err(|err| Error::Invalid {
+        version,
+        reason: err.to_string(),
+    })?;
+    Ok((events as usize, state))
+}
+
+// read_event reads an event from the log.
+fn read_event(data: &str) -> Result<Event, Error> {
+    read_entry(data).map(|(event, _)| event)
+}
+
+// read_entry reads an event from the log with its place in the audit trail.
+// Events have not changed shape since the log began, so there is nothing to
+// migrate; those logged before the audit trail are described afresh.
+fn read_entry(data: &str) -> Result<(Event, Audited), Error> {
+    let entry: StoredEntry =
+        serde_json::from_str(data).map_err(|err| Error::Malformed(err.to_string()))?;
+    if entry.version > VERSION {
+        return Err(Error::Newer(entry.version));
+    }
+    let version = entry.version;
+    let event: Event = serde_json::from_value(entry.event).map_err(|err| Error::Invalid {
+        version,
+        reason: err.to_string(),
+    })?;
+    let mut audited = entry.audited;
+    if audited.description.is_empty() {
+        audited.description = event.describe(&State::default());
+    }
+    Ok((event, audited))
+}
+
+// unwrap takes state out of its envelope, along with the version it was saved
+// at.
+fn unwrap(value: Value) -> (u32, Value) {
+    let version = value.get("version").and_then(Value::as_u64);
+    match (version, value) {
+        (Some(version), Value::Object(mut envelope)) if envelope.contains_key("state") => {
+            (version as u32, envelope.remove("state").unwrap_or_default())
+        }
+        (_, value) => (0, value),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Malformed(reason) => write!(f, "not valid JSON: {}", reason),
+            Error::Newer(version) => write!(
+                f,
+                "saved by a newer version of the app (version {}, this is {})",
+                version, VERSION
+            ),
+            Error::Invalid { version, reason } => {
+                write!(f, "not readable as version {}: {}", version, reason)
+            }
+            Error::Quarantined { at, reason } => write!(
+                f,
+                "saved data could not be read ({}) and has been kept in {}",
+                reason, at
+            ),
+            Error::Encoding(reason) => write!(f, "state could not be encoded: {}", reason),
+            Error::Storage(reason) => write!(f, "storage failed: {}", reason),
+            Error::Unknown(name) => write!(f, "there is no store called {:?}", name),
+            Error::Replay { at, reason } => {
+                write!(f, "event {} could not be replayed: {}", at, reason)
+            }
+            Error::Domain(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        Channel, Delivery, DeliveryStatus, LeaseId, Method, Money, Payment, SiteId, Subject,
+        Tenant, TenantId, Towards,
+    };
+    use crate::testing::{day, fixture};
+
+    // UNVERSIONED is state saved before envelopes, with tenants keyed by
+    // name, free-form contact details, leases kept beside the sites and
+    // amounts in whole dollars.
+    const UNVERSIONED: &str = r#"{
+        "tenants": {
+            "Jo": {"name": "Jo", "contact": "0400 123 456"}
+        },
+        "sites": {
+            "7": {"number": "7", "kind": "Cabin"}
+        },
+        "leases": [{
+            "tenant_name": "Jo",
+            "site_number": "7",
+            "term": {"start": "2024-01-01", "duration": 28, "rent": 250}
+        }],
+        "payments": [{
+            "tenant_name": "Jo",
+            "lease": {"site_number": "7", "start": "2024-01-01"},
+            "amount": 100,
+            "on": "2024-01-02",
+            "method": "Cash",
+            "reference": "",
+            "towards": "Rent"
+        }]
+    }"#;
+
+    #[test]
+    fn migrates_unversioned_state() {
+        let state = from_str(UNVERSIONED).unwrap();
+        let tenant = state.tenant(TenantId(1)).unwrap();
+        assert_eq!(tenant.name, "Jo");
+        assert_eq!(tenant.contact.phone().unwrap().as_str(), "+61400123456");
+        assert_eq!(tenant.contact.preferred, Channel::Sms);
+        let lease = state.lease(LeaseId(1)).unwrap();
+        assert_eq!((lease.tenant, lease.site), (TenantId(1), SiteId(1)));
+        assert_eq!(lease.term.rent, Money::from_units(250));
+        assert_eq!(state.payments[0].lease, LeaseId(1));
+        assert_eq!(state.payments[0].amount, Money::from_units(100));
+        assert_eq!(state.ledger.postings().len(), 1);
+    }
+
+    #[test]
+    fn reads_what_it_saves() {
+        let state = from_str(UNVERSIONED).unwrap();
+        let saved = to_string(&state).unwrap();
+        assert!(saved.starts_with(&format!("{{\"version\":{},", VERSION)));
+        assert_eq!(from_str(&saved).unwrap(), state);
+    }
+
+    #[test]
+    fn refuses_newer_and_unreadable_state() {
+        let newer = format!("{{\"version\":{},\"state\":{{}}}}", VERSION + 1);
+        assert_eq!(from_str(&newer), Err(Error::Newer(VERSION + 1)));
+        assert!(matches!(from_str("{\"tenants\""), Err(Error::Malformed(_))));
+        assert!(matches!(
+            from_str("{\"tenants\": []}"),
+            Err(Error::Invalid { version: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn refuses_state_whose_payments_cannot_be_posted() {
+        let unpostable = UNVERSIONED.replace("\"amount\": 100,", "\"amount\": 0,");
+        assert!(matches!(
+            from_str(&unpostable),
+            Err(Error::Invalid { version: 0, reason }) if reason.contains("$0.00")
+        ));
+    }
+
+    #[test]
+    fn quarantines_what_it_cannot_load() {
+        let mut store = Memory {
+            saved: Some("{\"tenants\"".into()),
+            ..Memory::default()
+        };
+        assert!(matches!(store.load(), Err(Error::Quarantined { .. })));
+        assert_eq!(store.quarantined, vec!["{\"tenants\"".to_string()]);
+
+        let mut state = store.load().unwrap().unwrap();
+        assert_eq!(state, State::default());
+        store.record(&mut state, tenant("Sam"), "manager").unwrap();
+        assert_eq!(store.load(), Ok(Some(state)));
+
+        let mut store = Memory::default();
+        assert_eq!(store.load(), Ok(None));
+    }
+
+    fn tenant(name: &str) -> Event {
+        Event::TenantRegistered(Tenant {
+            id: Default::default(),
+            name: name.into(),
+            contact: Default::default(),
+        })
+    }
+
+    #[test]
+    fn logs_only_changes_that_succeed() {
+        let mut store = Memory::default();
+        let mut state = from_str(UNVERSIONED).unwrap();
+        store.save(&state).unwrap();
+
+        assert_eq!(
+            store.record(&mut state, tenant("Jo"), "manager"),
+            Err(Error::Domain(DomainError::DuplicateTenant("Jo".into())))
+        );
+        assert!(store.log.is_empty());
+        assert_eq!(state.tenants.len(), 1);
+
+        let changes = store.record(&mut state, tenant("Sam"), "manager").unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(state.tenant_named("Sam").unwrap().id, TenantId(2));
+        assert_eq!(store.log.len(), 1);
+        assert_eq!(store.load(), Ok(Some(state)));
+    }
+
+    #[test]
+    fn takes_back_events_that_fail_part_way() {
+        let (mut state, at) = fixture("jo@example.com");
+        state.issue_invoice(at, day(1, 1)).unwrap();
+        let before = state.clone();
+        let mut store = Memory::default();
+        let delivery = Delivery {
+            on: day(1, 2),
+            to: "jo@example.com".into(),
+            status: DeliveryStatus::Sent,
+        };
+        let imported = Event::Imported {
+            deliveries: vec![(1, delivery.clone()), (2, delivery)],
+            notifications: vec![],
+        };
+        assert_eq!(
+            store.record(&mut state, imported, "manager"),
+            Err(Error::Domain(DomainError::UnknownInvoice(2)))
+        );
+        assert_eq!(state, before);
+        assert!(store.log.is_empty());
+    }
+
+    #[test]
+    fn replays_events_onto_the_last_snapshot() {
+        let mut store = Memory::default();
+        let mut state = State::default();
+        for n in 0..SNAPSHOT_EVERY + 2 {
+            store
+                .record(&mut state, tenant(&n.to_string()), "manager")
+                .unwrap();
+        }
+        let (covered, snapshot) = read_snapshot(store.saved.as_ref().unwrap()).unwrap();
+        assert_eq!(covered, SNAPSHOT_EVERY);
+        assert_eq!(snapshot.tenants.len(), SNAPSHOT_EVERY);
+        assert_eq!(store.load(), Ok(Some(state.clone())));
+
+        let bad =
+            "{\"version\":3,\"event\":{\"LeaseActivated\":{\"lease\":9,\"on\":\"2024-01-01\"}}}";
+        store.log.push(bad.into());
+        store.log.push(store.log[0].clone());
+        assert!(matches!(
+            store.load(),
+            Err(Error::Quarantined { reason, .. })
+                if reason.starts_with(&format!("event {} ", SNAPSHOT_EVERY + 3))
+        ));
+        let kept: Vec<String> = serde_json::from_str(&store.quarantined[0]).unwrap();
+        assert_eq!(kept, store.log[SNAPSHOT_EVERY + 2..]);
+
+        assert_eq!(store.load(), Ok(Some(state.clone())));
+        store.record(&mut state, tenant("Sam"), "manager").unwrap();
+        assert_eq!(store.load(), Ok(Some(state)));
+    }
+
+    #[test]
+    fn audits_each_change() {
+        let mut store = Memory::default();
+        let mut state = from_str(UNVERSIONED).unwrap();
+        store.save(&state).unwrap();
+        let payment = Event::PaymentRecorded(Payment {
+            tenant: TenantId(1),
+            lease: LeaseId(1),
+            amount: Money::from_units(50),
+            on: "2024-01-09".parse().unwrap(),
+            method: Method::Cash,
+            reference: "".into(),
+            towards: Towards::Rent,
+        });
+        store.record(&mut state, payment, "manager").unwrap();
+        store
+            .log
+            .push("{\"version\":3,\"event\":{\"RentFellDue\":{\"on\":\"2024-01-01\"}}}".into());
+
+        let audit = store.audit().unwrap();
+        let (paid, earlier) = (&audit[0], &audit[1]);
+        assert!(paid.at.is_some());
+        assert_eq!(paid.actor, "manager");
+        assert!(paid.description.starts_with("Recorded payment of"));
+        // The payment and its posting to the ledger.
+        assert_eq!(paid.changes.len(), 2);
+        assert!(paid.changes.iter().all(|c| c.before.is_none()));
+        for subject in [
+            Subject::Tenant(TenantId(1)),
+            Subject::Site(SiteId(1)),
+            Subject::Lease(LeaseId(1)),
+        ] {
+            assert!(paid.concerns(subject, &state));
+        }
+        assert!(!paid.concerns(Subject::Business, &state));
+
+        assert_eq!(earlier.at, None);
+        assert_eq!(earlier.description, "Posted rent due by 2024-01-01");
+    }
+}
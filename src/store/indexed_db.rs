@@ -0,0 +1,232 @@
+//! IndexedDB in the browser, which holds far more than local storage.
+//!
+//! IndexedDB only works asynchronously, so what was saved is read once, when
+//! the database is opened, and writes are made without waiting for them to
+//! finish. A write that fails is taken back out of what was read, along with
+//! any events logged after it. Every write after that is refused, reporting
+//! the failure, until what was saved is loaded again, so that nothing is
+//! logged onto state the database no longer holds.
+
+use super::local::js_error;
+use super::{Error, Store};
+use chrono::Local as Clock;
+use js_sys::{Array, Promise};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Event, IdbDatabase, IdbKeyRange, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+const DATABASE: &str = "avisha";
+
+/// OBJECTS is the object store the snapshot of state is saved in, under
+/// `KEY`.
+const OBJECTS: &str = "state";
+const KEY: &str = "self";
+
+/// EVENTS is the object store events are logged in, numbered from 0.
+const EVENTS: &str = "events";
+
+pub struct IndexedDb {
+    db: IdbDatabase,
+    written: Rc<RefCell<Written>>,
+    // on_complete and on_failure are called when a write finishes. They are
+    // kept for as long as writes may call them.
+    on_complete: Closure<dyn FnMut(Event)>,
+    on_failure: Closure<dyn FnMut(Event)>,
+}
+
+// Written is what has been saved, counting writes that have not finished.
+struct Written {
+    saved: Option<String>,
+    log: Vec<String>,
+    pending: Vec<(IdbTransaction, Undo)>,
+    failed: Option<String>,
+}
+
+// Undo is how to take a write back out of what was saved if it fails.
+enum Undo {
+    Write(Option<String>),
+    Append(usize),
+    Nothing,
+}
+
+impl IndexedDb {
+    /// open opens the database, creating it if need be, and reads what was
+    /// saved.
+    pub async fn open() -> Result<Self, Error> {
+        let factory = web_sys::window()
+            .and_then(|window| window.indexed_db().ok().flatten())
+            .ok_or_else(|| Error::Storage("IndexedDB is not available".into()))?;
+        let request = factory.open_with_u32(DATABASE, 2).map_err(js_error)?;
+        let upgrading = request.clone();
+        // Version 1 had no log. Creating a store that exists fails, which is
+        // no matter.
+        let upgrade = Closure::once_into_js(move || {
+            if let Ok(db) = upgrading.result() {
+                let db = db.unchecked_into::<IdbDatabase>();
+                let _ = db.create_object_store(OBJECTS);
+                let _ = db.create_object_store(EVENTS);
+            }
+        });
+        request.set_onupgradeneeded(Some(upgrade.unchecked_ref()));
+        let db: IdbDatabase = wait(&request).await?.unchecked_into();
+
+        let get = db
+            .transaction_with_str(OBJECTS)
+            .and_then(|transaction| transaction.object_store(OBJECTS))
+            .and_then(|objects| objects.get(&KEY.into()))
+            .map_err(js_error)?;
+        let saved = wait(&get).await?.as_string();
+
+        let get_all = db
+            .transaction_with_str(EVENTS)
+            .and_then(|transaction| transaction.object_store(EVENTS))
+            .and_then(|events| events.get_all())
+            .map_err(js_error)?;
+        let log = Array::from(&wait(&get_all).await?)
+            .iter()
+            .filter_map(|event| event.as_string())
+            .collect();
+
+        let written = Rc::new(RefCell::new(Written {
+            saved,
+            log,
+            pending: vec![],
+            failed: None,
+        }));
+        let completed = written.clone();
+        let on_complete = Closure::wrap(Box::new(move |event: Event| {
+            completed.borrow_mut().finish(&event);
+        }) as Box<dyn FnMut(Event)>);
+        let (failed, cleaning) = (written.clone(), db.clone());
+        let on_failure = Closure::wrap(Box::new(move |event: Event| {
+            let mut written = failed.borrow_mut();
+            let undo = match written.finish(&event) {
+                Some(undo) => undo,
+                // An error is reported to the transaction before it aborts.
+                None => return,
+            };
+            written.failed = Some("an earlier write to IndexedDB failed".to_string());
+            match undo {
+                Undo::Write(saved) => written.saved = saved,
+                // Events logged after the one that failed were replayed onto
+                // it, so they go too.
+                Undo::Append(n) => {
+                    written.log.truncate(n);
+                    let _ = cleaning
+                        .transaction_with_str_and_mode(EVENTS, IdbTransactionMode::Readwrite)
+                        .and_then(|transaction| transaction.object_store(EVENTS))
+                        .and_then(|events| {
+                            events.delete(&IdbKeyRange::lower_bound(&(n as f64).into())?.into())
+                        });
+                }
+                Undo::Nothing => {}
+            }
+        }) as Box<dyn FnMut(Event)>);
+        Ok(IndexedDb {
+            db,
+            written,
+            on_complete,
+            on_failure,
+        })
+    }
+
+    // put writes data under a key in an object store without waiting for it
+    // to be written, noting how to undo it if it fails. A write that failed
+    // since what was saved was last read is reported instead.
+    fn put(&self, store: &str, key: &JsValue, data: &str, undo: Undo) -> Result<(), Error> {
+        let mut written = self.written.borrow_mut();
+        if let Some(reason) = &written.failed {
+            return Err(Error::Storage(reason.clone()));
+        }
+        let transaction = self
+            .db
+            .transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)
+            .map_err(js_error)?;
+        transaction.set_oncomplete(Some(self.on_complete.as_ref().unchecked_ref()));
+        transaction.set_onerror(Some(self.on_failure.as_ref().unchecked_ref()));
+        transaction.set_onabort(Some(self.on_failure.as_ref().unchecked_ref()));
+        transaction
+            .object_store(store)
+            .and_then(|objects| objects.put_with_key(&data.into(), key))
+            .map_err(js_error)?;
+        written.pending.push((transaction, undo));
+        Ok(())
+    }
+}
+
+impl Written {
+    // finish forgets the write whose transaction an event is for, returning
+    // how to undo it, or nothing if it has already finished.
+    fn finish(&mut self, event: &Event) -> Option<Undo> {
+        let target: JsValue = event.current_target()?.into();
+        let ii = self
+            .pending
+            .iter()
+            .position(|(transaction, _)| JsValue::from(transaction) == target)?;
+        Some(self.pending.remove(ii).1)
+    }
+}
+
+impl Store for IndexedDb {
+    // Reading what was saved, which `load` does first, lets writes be made
+    // again after one has failed.
+    fn read(&mut self) -> Result<Option<String>, Error> {
+        let mut written = self.written.borrow_mut();
+        written.failed = None;
+        Ok(written.saved.clone())
+    }
+
+    fn write(&mut self, data: String) -> Result<(), Error> {
+        let saved = self.written.borrow().saved.clone();
+        self.put(OBJECTS, &KEY.into(), &data, Undo::Write(saved))?;
+        self.written.borrow_mut().saved = Some(data);
+        Ok(())
+    }
+
+    fn read_log(&mut self, from: usize) -> Result<Vec<String>, Error> {
+        Ok(self
+            .written
+            .borrow()
+            .log
+            .iter()
+            .skip(from)
+            .cloned()
+            .collect())
+    }
+
+    fn append(&mut self, event: String) -> Result<(), Error> {
+        let n = self.written.borrow().log.len();
+        self.put(EVENTS, &(n as f64).into(), &event, Undo::Append(n))?;
+        self.written.borrow_mut().log.push(event);
+        Ok(())
+    }
+
+    fn logged(&mut self) -> Result<usize, Error> {
+        Ok(self.written.borrow().log.len())
+    }
+
+    fn quarantine(&mut self, data: String) -> Result<String, Error> {
+        let key = format!("{}.quarantine.{}", KEY, Clock::now().timestamp());
+        self.put(OBJECTS, &key.as_str().into(), &data, Undo::Nothing)?;
+        Ok(format!("IndexedDB under {:?}", key))
+    }
+}
+
+// wait waits for a request to finish, returning its result.
+async fn wait(request: &IdbRequest) -> Result<JsValue, Error> {
+    let done = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    if JsFuture::from(done).await.is_err() {
+        let reason = match request.error() {
+            Ok(Some(err)) => err.message(),
+            _ => "an IndexedDB request failed".to_string(),
+        };
+        return Err(Error::Storage(reason));
+    }
+    request.result().map_err(js_error)
+}
@@ -0,0 +1,77 @@
+//! Local storage in the browser, which holds a few megabytes for each site.
+
+use super::{Error, Store};
+use chrono::Local as Clock;
+use wasm_bindgen::JsValue;
+use web_sys::Storage;
+
+/// KEY is where the snapshot of state is saved. Events are logged under
+/// `KEY.event.<n>`, numbered from 0.
+pub const KEY: &str = "yew.avisha.self";
+
+pub struct Local {
+    storage: Storage,
+    logged: usize,
+}
+
+impl Local {
+    pub fn new() -> Result<Self, Error> {
+        let storage = web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| Error::Storage("local storage is not available".into()))?;
+        let mut local = Local { storage, logged: 0 };
+        while local.event(local.logged)?.is_some() {
+            local.logged += 1;
+        }
+        Ok(local)
+    }
+
+    fn event(&self, n: usize) -> Result<Option<String>, Error> {
+        self.storage
+            .get_item(&format!("{}.event.{}", KEY, n))
+            .map_err(js_error)
+    }
+}
+
+impl Store for Local {
+    fn read(&mut self) -> Result<Option<String>, Error> {
+        self.storage.get_item(KEY).map_err(js_error)
+    }
+
+    fn write(&mut self, data: String) -> Result<(), Error> {
+        self.storage.set_item(KEY, &data).map_err(js_error)
+    }
+
+    fn read_log(&mut self, from: usize) -> Result<Vec<String>, Error> {
+        (from..self.logged)
+            .filter_map(|n| self.event(n).transpose())
+            .collect()
+    }
+
+    fn append(&mut self, event: String) -> Result<(), Error> {
+        let key = format!("{}.event.{}", KEY, self.logged);
+        self.storage.set_item(&key, &event).map_err(js_error)?;
+        self.logged += 1;
+        Ok(())
+    }
+
+    fn logged(&mut self) -> Result<usize, Error> {
+        Ok(self.logged)
+    }
+
+    fn quarantine(&mut self, data: String) -> Result<String, Error> {
+        let key = format!("{}.quarantine.{}", KEY, Clock::now().timestamp());
+        self.storage.set_item(&key, &data).map_err(js_error)?;
+        Ok(format!("local storage under {:?}", key))
+    }
+}
+
+// js_error describes an exception thrown by the browser, such as the one
+// for storage being full.
+pub(super) fn js_error(err: JsValue) -> Error {
+    let reason = js_sys::Reflect::get(&err, &"message".into())
+        .ok()
+        .and_then(|message| message.as_string())
+        .unwrap_or_else(|| format!("{:?}", err));
+    Error::Storage(reason)
+}
@@ -0,0 +1,220 @@
+//! Migrations of saved state from each earlier shape to the next.
+//!
+//! Each migration rewrites the JSON of state saved at one version into the
+//! shape of the version after it. State saved before versions were recorded
+//! is version 0, and may be in any shape up to version 3; the first
+//! migrations recognise what they have to change and leave the rest alone.
+
+use crate::domain::Contact;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// VERSION is the version of the state this build saves.
+pub const VERSION: u32 = 3;
+
+// MIGRATIONS are applied in order; the one at index n takes state saved at
+// version n to version n + 1.
+const MIGRATIONS: [fn(&mut Value); VERSION as usize] =
+    [leases_onto_sites, structured_contacts, surrogate_ids];
+
+/// upgrade brings state saved at the given version up to `VERSION`.
+pub fn upgrade(state: &mut Value, version: u32) {
+    for migrate in MIGRATIONS.iter().skip(version as usize) {
+        migrate(state);
+    }
+}
+
+// leases_onto_sites moves leases kept in one list beside the sites onto the
+// site they lease, in order of start date. A lease already on its site is not
+// repeated, and one whose site is gone is dropped.
+fn leases_onto_sites(state: &mut Value) {
+    let root = match state.as_object_mut() {
+        Some(root) => root,
+        None => return,
+    };
+    let leases = match root.remove("leases") {
+        Some(Value::Array(leases)) => leases,
+        _ => return,
+    };
+    let sites = match root.get_mut("sites").and_then(Value::as_object_mut) {
+        Some(sites) => sites,
+        None => return,
+    };
+    for lease in leases {
+        let site = sites
+            .get_mut(&text(&lease, "site_number"))
+            .and_then(Value::as_object_mut);
+        if let Some(site) = site {
+            let list = site.entry("leases").or_insert_with(|| Value::Array(vec![]));
+            if let Value::Array(list) = list {
+                if !list.contains(&lease) {
+                    list.push(lease);
+                    list.sort_by_key(start);
+                }
+            }
+        }
+    }
+}
+
+// structured_contacts replaces the free-form contact text of each tenant
+// with the email addresses and phone numbers recognised in it.
+fn structured_contacts(state: &mut Value) {
+    let tenants = match state.get_mut("tenants").and_then(Value::as_object_mut) {
+        Some(tenants) => tenants,
+        None => return,
+    };
+    for tenant in tenants.values_mut() {
+        let contact = match tenant.get("contact") {
+            Some(Value::String(details)) => Contact::from_text(details),
+            _ => continue,
+        };
+        if let (Some(tenant), Ok(contact)) = (tenant.as_object_mut(), serde_json::to_value(contact))
+        {
+            tenant.insert("contact".into(), contact);
+        }
+    }
+}
+
+// surrogate_ids gives ids to tenants, sites and leases in state saved when
+// tenants were keyed by name, sites by number and leases by their site and
+// start date, and rewrites every reference to them. State that already has
+// ids is left alone.
+fn surrogate_ids(state: &mut Value) {
+    let root = match state.as_object_mut() {
+        Some(root) => root,
+        None => return,
+    };
+    let keyed_by_name = ["tenants", "sites"].iter().any(|key| {
+        root.get(*key)
+            .and_then(Value::as_object)
+            .is_some_and(|m| m.values().any(|v| v.get("id").is_none()))
+    });
+    if !keyed_by_name {
+        return;
+    }
+
+    let tenants = rekey(root, "tenants");
+    let sites = rekey(root, "sites");
+
+    let mut all: Vec<(u32, &mut Value)> = vec![];
+    if let Some(Value::Object(map)) = root.get_mut("sites") {
+        for site in map.values_mut() {
+            let id = site.get("id").and_then(Value::as_u64).unwrap_or(0) as u32;
+            if let Some(Value::Array(list)) = site.get_mut("leases") {
+                all.extend(list.iter_mut().map(|l| (id, l)));
+            }
+        }
+    }
+    all.sort_by(|(a, x), (b, y)| (a, start(x)).cmp(&(b, start(y))));
+    let mut leases: HashMap<(String, String), u32> = HashMap::new();
+    for (ii, (_, lease)) in all.iter().enumerate() {
+        let key = (text(lease, "site_number"), start(lease));
+        leases.insert(key, ii as u32 + 1);
+    }
+    for (_, lease) in all {
+        let site_number = text(lease, "site_number");
+        let id = leases[&(site_number.clone(), start(lease))];
+        if let Some(lease) = lease.as_object_mut() {
+            lease.insert("id".into(), id.into());
+            if let Some(Value::String(date)) = lease.remove("renews") {
+                let renews = leases.get(&(site_number, date)).copied();
+                lease.insert("renews".into(), renews.map_or(Value::Null, Value::from));
+            }
+        }
+    }
+
+    let ids = Ids {
+        tenants,
+        sites,
+        leases,
+    };
+    for (key, value) in root.iter_mut() {
+        if key != "tenants" {
+            ids.rewrite(value);
+        }
+    }
+}
+
+struct Ids {
+    tenants: HashMap<String, u32>,
+    sites: HashMap<String, u32>,
+    leases: HashMap<(String, String), u32>,
+}
+
+impl Ids {
+    // rewrite replaces references by name, number or site and start date
+    // with ids, wherever they are.
+    fn rewrite(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) if map.len() == 2 && map.contains_key("site_number") => {
+                if let Some(Value::String(start)) = map.get("start") {
+                    let key = (text_in(map, "site_number"), start.clone());
+                    *value = self.leases.get(&key).copied().unwrap_or(0).into();
+                    return;
+                }
+                self.rewrite_fields(map);
+            }
+            Value::Object(map) => self.rewrite_fields(map),
+            Value::Array(list) => list.iter_mut().for_each(|v| self.rewrite(v)),
+            _ => {}
+        }
+    }
+
+    fn rewrite_fields(&self, map: &mut Map<String, Value>) {
+        if let Some(Value::String(name)) = map.remove("tenant_name") {
+            let id = self.tenants.get(&name).copied().unwrap_or(0);
+            map.insert("tenant".into(), id.into());
+        }
+        if let Some(number) = map.remove("site_number") {
+            let id = number
+                .as_str()
+                .map(|n| self.sites.get(n).copied().unwrap_or(0));
+            map.insert("site".into(), id.map_or(Value::Null, Value::from));
+        }
+        map.values_mut().for_each(|v| self.rewrite(v));
+    }
+}
+
+// rekey replaces a map keyed by name with one keyed by id, numbering entries
+// in order of name, and returns the id given to each name.
+fn rekey(root: &mut Map<String, Value>, key: &str) -> HashMap<String, u32> {
+    let mut ids = HashMap::new();
+    let mut entries: Vec<(String, Value)> = match root.remove(key) {
+        Some(Value::Object(map)) => map.into_iter().collect(),
+        _ => vec![],
+    };
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut map = Map::new();
+    for (ii, (name, mut entry)) in entries.into_iter().enumerate() {
+        let id = ii as u32 + 1;
+        if let Some(entry) = entry.as_object_mut() {
+            entry.insert("id".into(), id.into());
+        }
+        map.insert(id.to_string(), entry);
+        ids.insert(name, id);
+    }
+    root.insert(key.into(), Value::Object(map));
+    ids
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn text_in(map: &Map<String, Value>, key: &str) -> String {
+    map.get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn start(lease: &Value) -> String {
+    lease
+        .get("term")
+        .map(|term| text(term, "start"))
+        .unwrap_or_default()
+}
@@ -0,0 +1,263 @@
+use crate::domain::{Money, Rate, SiteId, State as AppState, Tariff, Utility};
+use crate::form;
+use crate::validate::Validate;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use strum::IntoEnumIterator;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yew_components::Select;
+use yewtil::NeqAssign;
+
+pub struct Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    pub link: ComponentLink<Self>,
+    pub props: Props<V>,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Model {
+    pub utility: Utility,
+    pub scope: Scope,
+    pub supply: String,
+    pub rate: String,
+}
+
+/// Scope is which sites a tariff applies to.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum Scope {
+    #[default]
+    Property,
+    /// Site carries the site's number to show alongside its id.
+    Site(SiteId, String),
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props<V>
+where
+    V: Validate + Clone,
+{
+    pub submit: Callback<Model>,
+    pub state: AppState,
+    pub validator: V,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    Utility(Utility),
+    Scope(Scope),
+    Supply(String),
+    Rate(String),
+}
+
+impl<V> Component for Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    type Message = Msg;
+    type Properties = Props<V>;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::Utility(v) => self.model.utility = v,
+                    Field::Scope(v) => self.model.scope = v,
+                    Field::Supply(v) => self.model.supply = v,
+                    Field::Rate(v) => self.model.rate = v,
+                };
+                self.validate_edit();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.props.validator.validate(&self.model).is_ok() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        let mut sites = self
+            .props
+            .state
+            .sites
+            .values()
+            .map(|s| (s.number.clone(), s.id))
+            .collect::<Vec<_>>();
+        sites.sort();
+        let scopes = std::iter::once(Scope::Property)
+            .chain(
+                sites
+                    .into_iter()
+                    .map(|(number, id)| Scope::Site(id, number)),
+            )
+            .collect::<Vec<_>>();
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Utility"}
+                    error=get_error("utility")
+                >
+                    <Select<Utility>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Utility(v)))
+                        options=Utility::iter().collect::<Vec<_>>()
+                        selected=&self.model.utility
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Applies To"}
+                    error=get_error("scope")
+                >
+                    <Select<Scope>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Scope(v)))
+                        options=scopes
+                        selected=&self.model.scope
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Supply Charge (per day)"}
+                    error=get_error("supply")
+                >
+                    <input
+                        type="text"
+                        placeholder="0.00"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Supply(v.value)))
+                        value=&self.model.supply
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Rate (per unit)"}
+                    error=get_error("rate")
+                >
+                    <input
+                        type="text"
+                        placeholder="0.28 or 10@0.25, 0.30 or peak 40%@0.35, off-peak 60%@0.20"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Rate(v.value)))
+                        value=&self.model.rate
+                    />
+                </form::Field>
+
+                <button
+                    type="submit"
+                    disabled={!self.errors.is_empty()}
+                >
+                    {"Set Tariff"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl<V> Form<V>
+where
+    V: Validate<Model = Model> + Clone + PartialEq + 'static,
+{
+    fn validate(&mut self) {
+        match self.props.validator.validate(&self.model) {
+            Err(errors) => self.errors = errors,
+            Ok(_) => self.errors.clear(),
+        };
+    }
+
+    // validate_edit ignores validation for fields not yet filled in.
+    fn validate_edit(&mut self) {
+        self.validate();
+
+        {
+            if self.model.supply.is_empty() {
+                self.errors.remove("supply");
+            }
+            if self.model.rate.is_empty() {
+                self.errors.remove("rate");
+            }
+        }
+    }
+}
+
+impl Model {
+    /// tariff parses the raw form input into a domain `Tariff`, keyed by
+    /// field name on failure.
+    pub fn tariff(&self) -> Result<Tariff, HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        let supply = if self.supply.trim().is_empty() {
+            Some(Money::ZERO)
+        } else {
+            self.supply.parse().ok()
+        };
+        if supply.is_none() {
+            errors.insert(
+                "supply".into(),
+                format!("must be an amount up to {}", Money::MAX),
+            );
+        }
+        let rate = match self.rate.parse::<Rate>() {
+            Ok(rate) => Some(rate),
+            Err(err) => {
+                errors.insert("rate".into(), err.to_string());
+                None
+            }
+        };
+
+        match (supply, rate) {
+            (Some(supply), Some(rate)) => Ok(Tariff {
+                utility: self.utility,
+                site: match &self.scope {
+                    Scope::Property => None,
+                    Scope::Site(id, _) => Some(*id),
+                },
+                supply,
+                rate,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Property => write!(f, "All sites"),
+            Scope::Site(_, number) => write!(f, "Site {}", number),
+        }
+    }
+}
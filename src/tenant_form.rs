@@ -1,8 +1,13 @@
+use crate::domain::contact::ParseError;
+use crate::domain::{Channel, Contact, EmergencyContact};
 use crate::form;
 use crate::validate::Validate;
 use std::mem;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
 use web_sys::{Event, FocusEvent};
 use yew::prelude::*;
+use yew_components::Select;
 use yewtil::NeqAssign;
 use std::collections::HashMap;
 
@@ -19,7 +24,15 @@ where
 #[derive(Default, Clone, PartialEq, Debug)]
 pub struct Model {
     pub name: String,
-    pub contact: String,
+    /// Email addresses, separated by commas.
+    pub emails: String,
+    /// Phone numbers, separated by commas.
+    pub phones: String,
+    pub address: String,
+    pub preferred: Channel,
+    pub emergency_name: String,
+    pub emergency_relationship: String,
+    pub emergency_phone: String,
 }
 
 #[derive(Properties, Clone, PartialEq)]
@@ -39,7 +52,13 @@ pub enum Msg {
 
 pub enum Field {
     Name(String),
-    Contact(String),
+    Emails(String),
+    Phones(String),
+    Address(String),
+    Preferred(Channel),
+    EmergencyName(String),
+    EmergencyRelationship(String),
+    EmergencyPhone(String),
 }
 
 impl<V> Component for Form<V>
@@ -62,7 +81,13 @@ where
             Msg::Edit(field) => {
                 match field {
                     Field::Name(value) => self.model.name = value,
-                    Field::Contact(value) => self.model.contact = value,
+                    Field::Emails(value) => self.model.emails = value,
+                    Field::Phones(value) => self.model.phones = value,
+                    Field::Address(value) => self.model.address = value,
+                    Field::Preferred(value) => self.model.preferred = value,
+                    Field::EmergencyName(value) => self.model.emergency_name = value,
+                    Field::EmergencyRelationship(value) => self.model.emergency_relationship = value,
+                    Field::EmergencyPhone(value) => self.model.emergency_phone = value,
                 };
                 self.validate_edit();
             }
@@ -74,7 +99,7 @@ where
                 if self.props.validator.validate(&self.model).is_ok() {
                     self.props
                         .submit
-                        .emit(mem::replace(&mut self.model, Model::default()));
+                        .emit(mem::take(&mut self.model));
                 }
             }
         }
@@ -109,16 +134,51 @@ where
                 </form::Field>
 
                 <form::Field
-                    label={"Contact"}
-                    error=get_error("contact")
+                    label={"Email"}
+                    error=get_error("emails")
                 >
                     <input
                         type="text"
-                        placeholder="Email or Phone"
-                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Contact(v.value)))
-                        value=&self.model.contact
+                        placeholder="Separate addresses with commas"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Emails(v.value)))
+                        value=&self.model.emails
                     />
                 </form::Field>
+
+                <form::Field
+                    label={"Phone"}
+                    error=get_error("phones")
+                >
+                    <input
+                        type="text"
+                        placeholder="Separate numbers with commas"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Phones(v.value)))
+                        value=&self.model.phones
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Postal Address"}
+                    error=get_error("address")
+                >
+                    <textarea
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Address(v.value)))
+                        value=&self.model.address
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Preferred Contact"}
+                    error=get_error("preferred")
+                >
+                    <Select<Channel>
+                        on_change=self.link.callback(|v| Msg::Edit(Field::Preferred(v)))
+                        options=Channel::iter().collect::<Vec<_>>()
+                        selected=&self.model.preferred
+                    />
+                </form::Field>
+
+                {self.emergency_fields()}
                 
                 <button type="submit">
                     {"Register"}
@@ -148,6 +208,119 @@ where
             if self.model.name.is_empty() {
                 self.errors.remove("name");
             }
+            if self.model.emergency_name.is_empty() {
+                self.errors.remove("emergency_name");
+            }
+            if self.model.emergency_phone.is_empty() {
+                self.errors.remove("emergency_phone");
+            }
         }
     }
+
+    fn emergency_fields(&self) -> Html {
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors
+                .get(field_name)
+                .map(|s| s.to_string())
+        };
+
+        html! {
+            <>
+                <form::Field
+                    label={"Emergency Contact"}
+                    error=get_error("emergency_name")
+                >
+                    <input
+                        type="text"
+                        placeholder="Name"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::EmergencyName(v.value)))
+                        value=&self.model.emergency_name
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Relationship"}
+                    error=get_error("emergency_relationship")
+                >
+                    <input
+                        type="text"
+                        placeholder="Optional"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::EmergencyRelationship(v.value)))
+                        value=&self.model.emergency_relationship
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Emergency Phone"}
+                    error=get_error("emergency_phone")
+                >
+                    <input
+                        type="text"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::EmergencyPhone(v.value)))
+                        value=&self.model.emergency_phone
+                    />
+                </form::Field>
+            </>
+        }
+    }
+}
+
+impl Model {
+    /// contact parses the contact details entered, returning errors keyed by
+    /// field.
+    pub fn contact(&self) -> Result<Contact, HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        let emails = parse_list(&self.emails).unwrap_or_else(|err| {
+            errors.insert("emails".into(), err.to_string());
+            vec![]
+        });
+        let phones = parse_list(&self.phones).unwrap_or_else(|err| {
+            errors.insert("phones".into(), err.to_string());
+            vec![]
+        });
+
+        let name = self.emergency_name.trim();
+        let relationship = self.emergency_relationship.trim();
+        let phone = self.emergency_phone.trim();
+        let emergency = if name.is_empty() && relationship.is_empty() && phone.is_empty() {
+            None
+        } else {
+            if name.is_empty() {
+                errors.insert("emergency_name".into(), "must be non-zero".into());
+            }
+            match phone.parse() {
+                Ok(phone) => Some(EmergencyContact {
+                    name: name.to_string(),
+                    relationship: relationship.to_string(),
+                    phone,
+                }),
+                Err(err) => {
+                    errors.insert("emergency_phone".into(), err.to_string());
+                    None
+                }
+            }
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Contact {
+            emails,
+            phones,
+            address: self.address.trim().to_string(),
+            preferred: self.preferred,
+            emergency,
+            notes: String::new(),
+        })
+    }
+}
+
+// parse_list parses each comma separated entry, skipping blanks.
+fn parse_list<T: FromStr<Err = ParseError>>(text: &str) -> Result<Vec<T>, ParseError> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::parse)
+        .collect()
 }
@@ -0,0 +1,131 @@
+use crate::form;
+use std::collections::HashMap;
+use std::mem;
+use web_sys::FocusEvent;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+/// Form collects the last day of occupancy and the reason for ending a lease
+/// early.
+pub struct Form {
+    pub link: ComponentLink<Self>,
+    pub props: Props,
+    pub model: Model,
+    pub errors: HashMap<String, String>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Model {
+    pub end: String,
+    pub reason: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub submit: Callback<Model>,
+}
+
+pub enum Msg {
+    Edit(Field),
+    Submit,
+}
+
+pub enum Field {
+    End(String),
+    Reason(String),
+}
+
+impl Component for Form {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Form {
+            props,
+            link,
+            model: Model::default(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(field) => {
+                match field {
+                    Field::End(v) => self.model.end = v,
+                    Field::Reason(v) => self.model.reason = v,
+                };
+                self.errors.clear();
+            }
+            Msg::Submit => {
+                self.validate();
+                if self.errors.is_empty() {
+                    self.props.submit.emit(mem::take(&mut self.model));
+                }
+            }
+        };
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::Submit
+        });
+
+        let get_error = |field_name: &str| -> Option<String> {
+            self.errors.get(field_name).map(|s| s.to_string())
+        };
+
+        html! {
+            <form onsubmit=submit>
+                <form::Field
+                    label={"Last Day"}
+                    error=get_error("end")
+                >
+                    <input
+                        type="date"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::End(v.value)))
+                        value=&self.model.end
+                    />
+                </form::Field>
+
+                <form::Field
+                    label={"Reason"}
+                    error=get_error("reason")
+                >
+                    <input
+                        type="text"
+                        placeholder="Reason for ending early"
+                        oninput=self.link.callback(|v: InputData| Msg::Edit(Field::Reason(v.value)))
+                        value=&self.model.reason
+                    />
+                </form::Field>
+
+                <button type="submit">
+                    {"Terminate"}
+                </button>
+            </form>
+        }
+    }
+}
+
+impl Form {
+    fn validate(&mut self) {
+        self.errors.clear();
+
+        if self.model.end.parse::<chrono::NaiveDate>().is_err() {
+            self.errors.insert("end".into(), "must be a date".into());
+        }
+
+        if self.model.reason.trim().is_empty() {
+            self.errors
+                .insert("reason".into(), "must be non-zero".into());
+        }
+    }
+}
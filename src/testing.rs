@@ -0,0 +1,78 @@
+//! State shared by tests.
+
+use crate::domain::{
+    Business, Contact, Days, Frequency, Lease, LeaseId, Money, Site, SiteId, SiteKind, State,
+    Status, Tenant, TenantId, Term,
+};
+use chrono::NaiveDate as Date;
+
+/// day is a day of 2024.
+pub fn day(month: u32, day: u32) -> Date {
+    Date::from_ymd_opt(2024, month, day).unwrap()
+}
+
+/// tenant is a tenant to register under the given name.
+pub fn tenant(name: &str) -> Tenant {
+    Tenant {
+        id: Default::default(),
+        name: name.into(),
+        contact: Default::default(),
+    }
+}
+
+/// site is a site to list under the given number.
+pub fn site(number: &str) -> Site {
+    Site {
+        number: number.into(),
+        ..Site::default()
+    }
+}
+
+/// lease is a draft lease at $250 a week.
+pub fn lease(tenant: TenantId, site: SiteId, start: Date, duration: Days) -> Lease {
+    Lease {
+        id: Default::default(),
+        tenant,
+        site,
+        term: Term {
+            start,
+            duration,
+            rent: Money::from_units(250),
+            frequency: Frequency::Weekly,
+        },
+        status: Status::Draft,
+        history: vec![],
+        renews: None,
+        utility: Default::default(),
+    }
+}
+
+/// fixture is a business with cabin 7 leased to Jo Citizen, reached at
+/// `contact`, for four weeks from the start of 2024.
+pub fn fixture(contact: &str) -> (State, LeaseId) {
+    let mut state = State::default();
+    state.business = Business {
+        name: "Avisha Holiday Park".into(),
+        address: "1 Beach Road\nSeaside 3000".into(),
+        phone: "03 9000 0000".into(),
+        email: "office@avisha.example".into(),
+        payment_instructions: "BSB 000-000, account 12345678".into(),
+    };
+    let tenant = state
+        .register_tenant(Tenant {
+            contact: Contact::from_text(contact),
+            ..tenant("Jo Citizen")
+        })
+        .unwrap();
+    let site = state
+        .list_site(Site {
+            kind: SiteKind::Cabin,
+            ..site("7")
+        })
+        .unwrap();
+    let at = state
+        .lease_site(lease(tenant, site, day(1, 1), 28))
+        .unwrap();
+    state.activate_lease(at, day(1, 1)).unwrap();
+    (state, at)
+}
@@ -1,5 +1,10 @@
-use crate::app::{Site, Tenant};
+use crate::domain::{Channel, Error as DomainError, Money, Site, SiteId, State, Tenant, TenantId};
+use crate::lease_form;
+use crate::meter_form;
+use crate::payment_form;
+use crate::reading_form;
 use crate::site_form;
+use crate::tariff_form;
 use crate::tenant_form::Model as TenantFormModel;
 use std::collections::HashMap;
 
@@ -11,7 +16,7 @@ pub trait Validate {
 
 #[derive(Clone, PartialEq)]
 pub struct TenantValidator {
-    pub tenants: HashMap<String, Tenant>,
+    pub tenants: HashMap<TenantId, Tenant>,
 }
 
 impl Validate for TenantValidator {
@@ -21,11 +26,27 @@ impl Validate for TenantValidator {
         let mut errors: HashMap<String, String> = HashMap::new();
 
         if m.name.is_empty() {
-            errors.insert("name".into(), format!("must be non-zero"));
+            errors.insert("name".into(), "must be non-zero".into());
         }
 
-        if self.tenants.contains_key(&m.name) {
-            errors.insert("name".into(), format!("must be unique"));
+        if self.tenants.values().any(|t| t.name == m.name) {
+            errors.insert("name".into(), "must be unique".into());
+        }
+
+        match m.contact() {
+            Err(parse_errors) => errors.extend(parse_errors),
+            // Tenants need not give contact details, but if they do the
+            // preferred channel must be one of them.
+            Ok(contact) if contact.emails.is_empty() && contact.phones.is_empty() => {}
+            Ok(contact) => match contact.preferred {
+                Channel::Email if contact.emails.is_empty() => {
+                    errors.insert("preferred".into(), "needs an email address".into());
+                }
+                Channel::Sms if contact.phones.is_empty() => {
+                    errors.insert("preferred".into(), "needs a phone number".into());
+                }
+                _ => {}
+            },
         }
 
         if !errors.is_empty() {
@@ -38,7 +59,7 @@ impl Validate for TenantValidator {
 
 #[derive(Clone, PartialEq)]
 pub struct SiteValidator {
-    pub sites: HashMap<String, Site>,
+    pub sites: HashMap<SiteId, Site>,
 }
 
 impl Validate for SiteValidator {
@@ -48,16 +69,205 @@ impl Validate for SiteValidator {
         let mut errors: HashMap<String, String> = HashMap::new();
 
         if m.number.is_empty() {
-            errors.insert("number".into(), format!("must be non-zero"));
+            errors.insert("number".into(), "must be non-zero".into());
         }
 
-        if self.sites.contains_key(&m.number) {
-            errors.insert("number".into(), format!("must be unique"));
+        if self.sites.values().any(|s| s.number == m.number) {
+            errors.insert("number".into(), "must be unique".into());
         }
 
         if let site_form::Kind::Other(k) = &m.kind {
             if k.is_empty() {
-                errors.insert("kind".into(), format!("must be non-zero"));
+                errors.insert("kind".into(), "must be non-zero".into());
+            }
+        }
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct LeaseValidator {
+    pub state: State,
+}
+
+impl Validate for LeaseValidator {
+    type Model = lease_form::Model;
+
+    fn validate(&self, m: &Self::Model) -> Result<(), HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        if m.site.number.is_empty() {
+            errors.insert("site".into(), "must be selected".into());
+        }
+
+        if m.tenant.name.is_empty() {
+            errors.insert("tenant".into(), "must be selected".into());
+        }
+
+        match m.lease() {
+            Err(parse_errors) => errors.extend(parse_errors),
+            Ok(lease) => {
+                if let Err(err) = lease.term.check() {
+                    errors.insert("duration".into(), err.to_string());
+                } else if let Some(existing) = self.state.conflicting_lease(&lease) {
+                    errors.insert(
+                        "start".into(),
+                        DomainError::LeaseConflict(Box::new(existing.clone())).to_string(),
+                    );
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct MeterValidator {
+    pub sites: HashMap<SiteId, Site>,
+}
+
+impl Validate for MeterValidator {
+    type Model = meter_form::Model;
+
+    fn validate(&self, m: &Self::Model) -> Result<(), HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        if m.site.number.is_empty() {
+            errors.insert("site".into(), "must be selected".into());
+        }
+
+        if m.name.trim().is_empty() {
+            errors.insert("name".into(), "must be non-zero".into());
+        }
+
+        if let Some(site) = self.sites.get(&m.site.id) {
+            if site.meter(m.name.trim()).is_some() {
+                errors.insert("name".into(), "must be unique on the site".into());
+            }
+        }
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ReadingValidator {
+    pub sites: HashMap<SiteId, Site>,
+}
+
+impl Validate for ReadingValidator {
+    type Model = reading_form::Model;
+
+    fn validate(&self, m: &Self::Model) -> Result<(), HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        if m.site.number.is_empty() {
+            errors.insert("site".into(), "must be selected".into());
+        }
+
+        if m.meter.name.is_empty() {
+            errors.insert("meter".into(), "must be selected".into());
+        }
+
+        let last = self
+            .sites
+            .get(&m.site.id)
+            .and_then(|s| s.meter(&m.meter.name))
+            .and_then(|meter| meter.readings.last());
+
+        match m.reading() {
+            Err(parse_errors) => errors.extend(parse_errors),
+            Ok(reading) => {
+                if let Some(last) = last {
+                    if reading.on <= last.on {
+                        errors.insert("on".into(), format!("must be after {}", last.on));
+                    }
+                    if reading.value < last.value {
+                        errors.insert("value".into(), format!("must be at least {}", last.value));
+                    }
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TariffValidator;
+
+impl Validate for TariffValidator {
+    type Model = tariff_form::Model;
+
+    fn validate(&self, m: &Self::Model) -> Result<(), HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        match m.tariff() {
+            Err(parse_errors) => errors.extend(parse_errors),
+            Ok(tariff) => {
+                if let Err(err) = tariff.check() {
+                    errors.insert("rate".into(), err.to_string());
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct PaymentValidator {
+    pub state: State,
+}
+
+impl Validate for PaymentValidator {
+    type Model = payment_form::Model;
+
+    fn validate(&self, m: &Self::Model) -> Result<(), HashMap<String, String>> {
+        let mut errors: HashMap<String, String> = HashMap::new();
+
+        if m.tenant.name.is_empty() {
+            errors.insert("tenant".into(), "must be selected".into());
+        }
+
+        match m.payment() {
+            Err(parse_errors) => errors.extend(parse_errors),
+            Ok(payment) => {
+                if payment.amount <= Money::ZERO {
+                    errors.insert("amount".into(), "must be more than zero".into());
+                }
+                match self.state.lease(payment.lease) {
+                    None => {
+                        errors.insert("lease".into(), "must be selected".into());
+                    }
+                    Some(lease) => {
+                        if lease.tenant != payment.tenant {
+                            errors.insert("lease".into(), "must belong to the tenant".into());
+                        }
+                    }
+                }
             }
         }
 